@@ -1,5 +1,19 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use revenq::Queue;
+use criterion::{
+    criterion_group, criterion_main,
+    measurement::{Measurement, ValueFormatter},
+    Criterion, Throughput,
+};
+use event_listener::Event;
+use revenq::{Queue, RevisionRef, Subscriber};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+};
 
 #[inline]
 fn skip_and_publish<T: Send + 'static>(q: &mut Queue<T>) {
@@ -10,11 +24,11 @@ fn queue_bench(c: &mut Criterion) {
     c.bench_function("queue-simple", |b| {
         b.iter(|| {
             let mut q = Queue::new();
-            q.enqueue(0);
+            q.enqueue(0).unwrap();
             skip_and_publish(&mut q);
             let mut l = q.clone();
             l.next();
-            q.enqueue(1);
+            q.enqueue(1).unwrap();
             skip_and_publish(&mut q);
             l.next();
         })
@@ -23,7 +37,7 @@ fn queue_bench(c: &mut Criterion) {
     c.bench_function("queue-send", |b| {
         b.iter(|| {
             let mut q = Queue::new();
-            q.enqueue(0);
+            q.enqueue(0).unwrap();
             q.next();
         })
     });
@@ -34,7 +48,7 @@ fn queue_bench(c: &mut Criterion) {
             let mut l1 = q.clone();
             let mut l2 = q.clone();
 
-            q.enqueue(0);
+            q.enqueue(0).unwrap();
             skip_and_publish(&mut q);
             l1.next();
             l2.next();
@@ -45,7 +59,7 @@ fn queue_bench(c: &mut Criterion) {
         b.iter(|| {
             use std::thread;
             let spt = |mut q: Queue<u32>, publiv: &[u32]| {
-                q.pending = publiv.iter().map(|i| *i).collect();
+                q.enqueue_many(publiv.iter().copied()).unwrap();
                 let plvl = publiv.len();
                 thread::spawn(move || {
                     let mut c = Vec::with_capacity(plvl);
@@ -66,7 +80,405 @@ fn queue_bench(c: &mut Criterion) {
             th2.join().unwrap();
         })
     });
+
+    for &subscribers in &[4, 64] {
+        c.bench_function(&format!("queue-fanout-{}", subscribers), |b| {
+            b.iter(|| {
+                use std::thread;
+                let (mut publ, sub) = revenq::channel::<u32>();
+                let ths: Vec<_> = (0..subscribers)
+                    .map(|_| {
+                        let mut sub = sub.clone();
+                        thread::spawn(move || {
+                            let mut c = 0;
+                            while let Some(x) = sub.next_blocking() {
+                                c += *x;
+                            }
+                            c
+                        })
+                    })
+                    .collect();
+                drop(sub);
+                for i in 0..100 {
+                    publ.enqueue(i).unwrap();
+                    publ.flush().unwrap();
+                }
+                drop(publ);
+                for th in ths {
+                    assert_eq!(th.join().unwrap(), 4950);
+                }
+            })
+        });
+    }
+
+    // one subscriber drains a backlog while many others are idle,
+    // which shouldn't wake the idle ones
+    c.bench_function("queue-drain-64-idle", |b| {
+        b.iter_custom(|iters| {
+            use std::{thread, time::Instant};
+            let (mut publ, mut sub) = revenq::channel::<u64>();
+            let ths: Vec<_> = (0..64)
+                .map(|_| {
+                    let mut sub = sub.clone();
+                    thread::spawn(move || while sub.next_blocking().is_some() {})
+                })
+                .collect();
+            publ.enqueue_many(0..iters).unwrap();
+            publ.flush().unwrap();
+
+            let start = Instant::now();
+            for _ in 0..iters {
+                futures_lite::future::block_on(sub.next_async()).unwrap();
+            }
+            let elapsed = start.elapsed();
+
+            drop(publ);
+            for th in ths {
+                th.join().unwrap();
+            }
+            elapsed
+        })
+    });
+
+    // a queue which never consumes publishes behind a growing backlog,
+    // which shouldn't be walked on each publish
+    for &backlog in &[0, 100_000] {
+        c.bench_function(&format!("queue-publish-backlog-{}", backlog), |b| {
+            b.iter_custom(|iters| {
+                use std::time::Instant;
+                let mut q = Queue::new();
+                let mut stale = q.clone();
+                q.enqueue_many(0..backlog).unwrap();
+                q.publish().unwrap();
+
+                let start = Instant::now();
+                for i in 0..iters {
+                    stale.enqueue(i).unwrap();
+                    stale.publish().unwrap();
+                }
+                start.elapsed()
+            })
+        });
+    }
+
+    // the single-threaded hot paths, while lagging clones retain the chain
+    c.bench_function("queue-publish-lagging-4", |b| {
+        b.iter_custom(|iters| {
+            use std::time::Instant;
+            let mut q = Queue::new();
+            let mut lagging: Vec<_> = (0..4).map(|_| q.clone()).collect();
+
+            let start = Instant::now();
+            for i in 0..iters {
+                q.enqueue(i).unwrap();
+                skip_and_publish(&mut q);
+            }
+            let elapsed = start.elapsed();
+
+            for l in &mut lagging {
+                skip_and_publish(l);
+            }
+            elapsed
+        })
+    });
+
+    c.bench_function("queue-consume-lagging-4", |b| {
+        b.iter_custom(|iters| {
+            use std::time::Instant;
+            let mut q = Queue::new();
+            let mut lagging: Vec<_> = (0..4).map(|_| q.clone()).collect();
+            for i in 0..iters {
+                q.enqueue(i).unwrap();
+            }
+            skip_and_publish(&mut q);
+
+            // the time per consumed revision and clone
+            let start = Instant::now();
+            for l in &mut lagging {
+                skip_and_publish(l);
+            }
+            start.elapsed() / 4
+        })
+    });
+}
+
+// the number of wakeups of the counted tasks which were run via run_tasks
+static WAKEUPS: AtomicU64 = AtomicU64::new(0);
+
+// set while yield_times wakes its own task, which isn't counted
+static YIELDING: AtomicBool = AtomicBool::new(false);
+
+struct TaskWaker {
+    woken: AtomicBool,
+    counted: bool,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.counted && !YIELDING.load(Ordering::SeqCst) {
+            WAKEUPS.fetch_add(1, Ordering::SeqCst);
+        }
+        self.woken.store(true, Ordering::SeqCst);
+    }
+}
+
+type Task<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// Runs the tasks on the current thread until all of them completed,
+/// a task is only polled again after it was woken. Only the wakeups
+/// of the tasks which are marked with `true` are counted.
+fn run_tasks(tasks: Vec<(Task<'_>, bool)>) {
+    let mut tasks: Vec<_> = tasks
+        .into_iter()
+        .map(|(task, counted)| {
+            let waker = Arc::new(TaskWaker {
+                woken: AtomicBool::new(true),
+                counted,
+            });
+            (Some(task), Arc::clone(&waker), Waker::from(waker))
+        })
+        .collect();
+    while tasks.iter().any(|i| i.0.is_some()) {
+        let mut polled = false;
+        for (task, flag, waker) in &mut tasks {
+            if task.is_some() && flag.woken.swap(false, Ordering::SeqCst) {
+                polled = true;
+                let mut cx = Context::from_waker(waker);
+                if task.as_mut().unwrap().as_mut().poll(&mut cx).is_ready() {
+                    *task = None;
+                }
+            }
+        }
+        assert!(polled, "all remaining tasks wait forever");
+    }
+}
+
+/// Yields to the other tasks `n` times, these wakeups aren't counted.
+async fn yield_times(n: usize) {
+    for _ in 0..n {
+        let mut yielded = false;
+        futures_lite::future::poll_fn(|cx| {
+            if yielded {
+                return Poll::Ready(());
+            }
+            yielded = true;
+            YIELDING.store(true, Ordering::SeqCst);
+            cx.waker().wake_by_ref();
+            YIELDING.store(false, Ordering::SeqCst);
+            Poll::Pending
+        })
+        .await;
+    }
+}
+
+/// Receives like `next_async` did in revenq 0.4, which is emulated for
+/// comparison: publishing wakes all waiting subscribers via `event`,
+/// and each subscriber which received something wakes another one.
+async fn next_with_relay(sub: &mut Subscriber<u32>, event: &Event) -> Option<RevisionRef<u32>> {
+    let mut listener = None;
+    loop {
+        if let ret @ Some(_) = sub.next() {
+            event.notify(1);
+            return ret;
+        } else if sub.publisher_count() == 0 {
+            return sub.next();
+        }
+        match listener.take() {
+            None => listener = Some(event.listen()),
+            Some(l) => l.await,
+        }
+    }
+}
+
+/// Publishes 100 revisions to 64 subscribers, every second one yields
+/// `delay` times after each revision, and the publisher yields `pace` times
+/// after each publish. Only the wakeups of the subscribers are counted.
+/// If `relay` is set, they receive via next_with_relay.
+fn fanout(relay: bool, delay: usize, pace: usize) {
+    let event = Event::new();
+    let (mut publ, sub) = revenq::channel::<u32>();
+    let mut tasks: Vec<(Task<'_>, bool)> = (0..64)
+        .map(|i| {
+            let mut sub = sub.clone();
+            let event = &event;
+            let delay = if i % 2 == 0 { 0 } else { delay };
+            let task: Task<'_> = Box::pin(async move {
+                loop {
+                    let x = if relay {
+                        next_with_relay(&mut sub, event).await
+                    } else {
+                        sub.next_async().await
+                    };
+                    if x.is_none() {
+                        break;
+                    }
+                    yield_times(delay).await;
+                }
+            });
+            (task, true)
+        })
+        .collect();
+    drop(sub);
+    let event = &event;
+    let task: Task<'_> = Box::pin(async move {
+        for i in 0..100 {
+            publ.enqueue(i).unwrap();
+            publ.flush().unwrap();
+            event.notify(usize::MAX);
+            yield_times(pace).await;
+        }
+        drop(publ);
+        event.notify(usize::MAX);
+    });
+    tasks.push((task, false));
+    run_tasks(tasks);
+}
+
+/// Measures the number of task wakeups, see run_tasks.
+struct Wakeups;
+
+impl Measurement for Wakeups {
+    type Intermediate = u64;
+    type Value = u64;
+
+    fn start(&self) -> u64 {
+        WAKEUPS.load(Ordering::SeqCst)
+    }
+
+    fn end(&self, start: u64) -> u64 {
+        WAKEUPS.load(Ordering::SeqCst) - start
+    }
+
+    fn add(&self, v1: &u64, v2: &u64) -> u64 {
+        v1 + v2
+    }
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn to_f64(&self, value: &u64) -> f64 {
+        // exact for any realistic number of wakeups
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &WakeupFormatter
+    }
+}
+
+struct WakeupFormatter;
+
+impl ValueFormatter for WakeupFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "wakeups"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        _throughput: &Throughput,
+        _values: &mut [f64],
+    ) -> &'static str {
+        "wakeups"
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "wakeups"
+    }
+}
+
+// these benchmarks report the number of wakeups per iteration, not the time
+fn wakeup_bench(c: &mut Criterion<Wakeups>) {
+    // each publish should wake each waiting subscriber once
+    c.bench_function("wakeups-fanout-64", |b| b.iter(|| fanout(false, 0, 1)));
+    // the notification scheme of revenq 0.4 needs as many wakeups here,
+    // because each relayed notification is taken by the next publish
+    c.bench_function("wakeups-fanout-64-relay", |b| b.iter(|| fanout(true, 0, 1)));
+
+    // if some subscribers are slower, and the publisher doesn't publish
+    // right away, the relayed notifications wake idle subscribers
+    c.bench_function("wakeups-fanout-64-mixed", |b| {
+        b.iter(|| fanout(false, 4, 3))
+    });
+    c.bench_function("wakeups-fanout-64-mixed-relay", |b| {
+        b.iter(|| fanout(true, 4, 3))
+    });
+
+    // a full bounded queue should wake its publisher once the slowest
+    // subscriber advanced, not whenever any subscriber advanced
+    c.bench_function("wakeups-bounded-16", |b| {
+        b.iter(|| {
+            let (mut publ, sub) = Queue::bounded(4).split();
+            let mut tasks: Vec<(Task<'_>, bool)> = (0..16)
+                .map(|i| {
+                    let mut sub = sub.clone();
+                    // one subscriber is much slower than the others
+                    let delay = if i == 0 { 4 } else { 0 };
+                    let task: Task<'_> = Box::pin(async move {
+                        while sub.next_async().await.is_some() {
+                            yield_times(delay).await;
+                        }
+                    });
+                    (task, false)
+                })
+                .collect();
+            drop(sub);
+            let task: Task<'_> = Box::pin(async move {
+                for i in 0..100 {
+                    publ.enqueue(i).unwrap();
+                    publ.flush_async().await.unwrap();
+                }
+            });
+            tasks.push((task, true));
+            run_tasks(tasks);
+        })
+    });
+
+    // publish_and_wait should only be woken when a subscriber
+    // consumed the published revisions, not on every consumed revision
+    c.bench_function("wakeups-publish-and-wait-16", |b| {
+        b.iter(|| {
+            let mut q = Queue::new();
+            let mut tasks: Vec<(Task<'_>, bool)> = (0..16)
+                .map(|_| {
+                    // the clones can publish, too, thus they wouldn't
+                    // detect the end of the queue
+                    let mut sub = q.clone();
+                    let task: Task<'_> = Box::pin(async move {
+                        for _ in 0..100 {
+                            sub.next_async().await.unwrap();
+                            yield_times(1).await;
+                        }
+                    });
+                    (task, false)
+                })
+                .collect();
+            let task: Task<'_> = Box::pin(async move {
+                for i in 0..10 {
+                    q.enqueue_many(i * 10..(i + 1) * 10).unwrap();
+                    q.publish_and_wait().await.unwrap();
+                }
+            });
+            tasks.push((task, true));
+            run_tasks(tasks);
+        })
+    });
 }
 
 criterion_group!(benches, queue_bench);
-criterion_main!(benches);
+// the counts don't vary, which the plots can't handle, thus
+// they are disabled after the command line arguments were applied
+fn wakeups() {
+    let mut c = Criterion::default()
+        .with_measurement(Wakeups)
+        .configure_from_args()
+        .without_plots();
+    wakeup_bench(&mut c);
+}
+
+criterion_main!(benches, wakeups);
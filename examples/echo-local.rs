@@ -14,14 +14,14 @@ async fn server(conns: usize, ex: &Executor<'static>) {
             let mut queue = revenq::Queue::<u8>::new();
             let mut queue2 = queue.clone();
             for _ in 0..msg_per_client {
-                queue.enqueue(b'a');
+                queue.enqueue(b'a').unwrap();
             }
             queue.next();
             drop(queue);
             let mut queue_answ = revenq::Queue::<u8>::new();
             let mut queue_answ2 = queue_answ.clone();
             while let Some(x) = queue2.next_async().await {
-                queue_answ.enqueue(*x);
+                queue_answ.enqueue(*x).unwrap();
             }
             queue_answ.next();
             drop(queue_answ);
@@ -13,13 +13,13 @@ async fn server(conns: usize, ex: &Executor<'static>) {
         let mut queue2 = queue.clone();
         ex.spawn(async move {
             while let Some(x) = queue2.next_async().await {
-                queue2.enqueue(*x);
+                queue2.enqueue(*x).unwrap();
             }
         })
         .detach();
         clients.push(ex.spawn(async move {
             for _ in 0..msg_per_client {
-                queue.enqueue(b'a');
+                queue.enqueue(b'a').unwrap();
                 assert_eq!(*queue.next_async().await.unwrap(), b'a');
             }
         }))
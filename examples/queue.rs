@@ -8,7 +8,7 @@ fn main() {
                 let mut c = Vec::new();
                 let plvl = publiv.len();
                 for i in publiv {
-                    q.enqueue(i);
+                    q.enqueue(i).unwrap();
                 }
                 while c.len() < plvl {
                     q.print_debug(
@@ -0,0 +1,56 @@
+use crate::{NextRevision, RevisionRef, Shared};
+use alloc::sync::Arc;
+
+/// A bookmark of a position in the revision chain,
+/// see [`Queue::bookmark`](crate::Queue::bookmark).
+///
+/// A cursor retains all revisions following its position, and can be
+/// iterated independently of the queue it was created from. Iterating it
+/// never publishes anything, and never waits for new revisions.
+#[derive(Debug)]
+pub struct Cursor<T> {
+    pub(crate) next: NextRevision<T>,
+    pub(crate) pos: u64,
+    pub(crate) shared: Arc<Shared>,
+}
+
+impl<T> Clone for Cursor<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Cursor {
+            next: Arc::clone(&self.next),
+            pos: self.pos,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Iterator for Cursor<T> {
+    type Item = RevisionRef<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<RevisionRef<T>> {
+        let ret = RevisionRef::new_and_forward(&mut self.next)?;
+        self.pos += 1;
+        Some(ret)
+    }
+}
+
+impl<T> Cursor<T> {
+    /// Returns the sequence number of the next revision this cursor would
+    /// yield, see [`Queue::position`](crate::Queue::position).
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the next revision without moving this cursor.
+    pub fn peek(&self) -> Option<RevisionRef<T>> {
+        match self.next.get() {
+            Some(Some(_)) => Some(RevisionRef {
+                inner: Arc::clone(&self.next),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{PendingBuffer, Queue, RevisionRef};
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+/// An owning reference to a single event inside of a revision,
+/// see [`Queue::events`].
+///
+/// It keeps the revision alive, thus the event is never copied.
+pub struct EventRef<C, E> {
+    rev: RevisionRef<C>,
+
+    // the index of the event in the data of $rev
+    idx: usize,
+
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<C, E> Clone for EventRef<C, E> {
+    #[inline]
+    fn clone(&self) -> Self {
+        EventRef {
+            rev: self.rev.clone(),
+            idx: self.idx,
+            _event: PhantomData,
+        }
+    }
+}
+
+impl<C: AsRef<[E]>, E> core::ops::Deref for EventRef<C, E> {
+    type Target = E;
+
+    #[inline]
+    fn deref(&self) -> &E {
+        &(*self.rev).as_ref()[self.idx]
+    }
+}
+
+impl<C: AsRef<[E]>, E: fmt::Debug> fmt::Debug for EventRef<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<C, E> EventRef<C, E> {
+    /// Returns the revision which contains this event.
+    #[inline]
+    pub fn revision(this: &Self) -> &RevisionRef<C> {
+        &this.rev
+    }
+}
+
+/// An iterator over the events of all consumed revisions,
+/// see [`Queue::events`].
+///
+/// The events are only borrowed from the returned [`EventRef`]s,
+/// thus they can't outlive their revision:
+///
+/// ```compile_fail
+/// let mut q = revenq::Queue::new();
+/// let mut l = q.clone();
+/// q.enqueue(vec![1u32]).unwrap();
+/// q.try_publish().unwrap();
+/// let event: &u32 = {
+///     let event = l.events().next().unwrap();
+///     &*event
+/// };
+/// assert_eq!(*event, 1);
+/// ```
+pub struct Events<'a, C, E, P = Vec<C>> {
+    queue: &'a mut Queue<C, P>,
+
+    // the current revision and the index of its next event
+    cur: Option<(RevisionRef<C>, usize)>,
+
+    _events: PhantomData<fn() -> E>,
+}
+
+impl<'a, C, E, P> fmt::Debug for Events<'a, C, E, P>
+where
+    C: fmt::Debug,
+    P: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Events")
+            .field("queue", &self.queue)
+            .field("revision", &self.cur.as_ref().map(|i| &i.0))
+            .finish()
+    }
+}
+
+impl<'a, C, E, P: PendingBuffer<C>> Events<'a, C, E, P>
+where
+    C: AsRef<[E]>,
+{
+    pub(crate) fn new(queue: &'a mut Queue<C, P>) -> Self {
+        Events {
+            queue,
+            cur: None,
+            _events: PhantomData,
+        }
+    }
+
+    /// Returns the next event of the current revision, if any.
+    fn next_intern(&mut self) -> Option<EventRef<C, E>> {
+        let (rev, idx) = self.cur.as_mut()?;
+        if *idx < (**rev).as_ref().len() {
+            *idx += 1;
+            Some(EventRef {
+                rev: rev.clone(),
+                idx: *idx - 1,
+                _event: PhantomData,
+            })
+        } else {
+            self.cur = None;
+            None
+        }
+    }
+
+    /// Waits asynchronously for the next event,
+    /// see [`Queue::next_async`](crate::Queue::next_async).
+    pub async fn next_async(&mut self) -> Option<EventRef<C, E>> {
+        loop {
+            if let ret @ Some(_) = self.next_intern() {
+                return ret;
+            }
+            self.cur = Some((self.queue.next_async().await?, 0));
+        }
+    }
+}
+
+impl<'a, C, E, P: PendingBuffer<C>> Iterator for Events<'a, C, E, P>
+where
+    C: AsRef<[E]>,
+{
+    type Item = EventRef<C, E>;
+
+    fn next(&mut self) -> Option<EventRef<C, E>> {
+        loop {
+            if let ret @ Some(_) = self.next_intern() {
+                return ret;
+            }
+            self.cur = Some((self.queue.next()?, 0));
+        }
+    }
+}
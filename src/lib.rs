@@ -17,9 +17,41 @@ extern crate alloc;
 extern crate core;
 
 use alloc::{sync::Arc, vec::Vec};
-use core::{fmt, marker::Unpin};
-use event_listener::Event;
+use core::{
+    convert::TryFrom,
+    fmt,
+    future::Future,
+    marker::Unpin,
+    pin::Pin,
+    sync::atomic::{fence, AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use event_listener::EventListener;
 use once_cell::sync::OnceCell;
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+mod cursor;
+mod events;
+mod outbox;
+mod own;
+mod pending;
+mod retention;
+mod split;
+mod tail;
+mod waiters;
+pub use cursor::Cursor;
+pub use events::{EventRef, Events};
+use outbox::{Outbox, Publish};
+use own::{OwnRevisions, Walk};
+pub use pending::PendingBuffer;
+pub use retention::Replay;
+use retention::{Next, Retention};
+pub use split::{channel, Publisher, SharedPublisher, Subscriber};
+use tail::Tail;
+use waiters::Waiters;
 
 fn perfect_unreachable() -> ! {
     if core::cfg!(debug_assertions) {
@@ -29,14 +61,57 @@ fn perfect_unreachable() -> ! {
     }
 }
 
-type NextRevision<T> = Arc<OnceCell<RevisionNode<T>>>;
+// a cell containing `None` terminates the chain, see `Queue::close`
+type NextRevision<T> = Arc<OnceCell<Option<RevisionNode<T>>>>;
 
 #[derive(Clone, Debug)]
 struct RevisionNode<T> {
-    next: NextRevision<T>,
+    next: Link<T>,
+    seq: u64,
+    // the queue which it belongs to, see Queue::resume_after
+    queue: u64,
+    // the queue instance which published it, or 0, see Queue::rewind_to
+    origin: u64,
     data: T,
 }
 
+/// The link from a revision to the following one.
+///
+/// Dropping it unlinks the following revisions iteratively, as long as
+/// they aren't referenced elsewhere. Otherwise dropping a long chain of
+/// revisions would recurse once per revision and overflow the stack.
+#[derive(Clone, Debug)]
+struct Link<T>(NextRevision<T>);
+
+impl<T> Default for Link<T> {
+    #[inline]
+    fn default() -> Self {
+        Link(Arc::new(OnceCell::default()))
+    }
+}
+
+impl<T> core::ops::Deref for Link<T> {
+    type Target = NextRevision<T>;
+
+    #[inline(always)]
+    fn deref(&self) -> &NextRevision<T> {
+        &self.0
+    }
+}
+
+impl<T> Drop for Link<T> {
+    fn drop(&mut self) {
+        let mut cur = Arc::get_mut(&mut self.0).and_then(OnceCell::take).flatten();
+        while let Some(mut node) = cur {
+            // take the successor out of $node before $node gets dropped,
+            // which leaves an empty cell behind, thus no recursion happens
+            cur = Arc::get_mut(&mut node.next.0)
+                .and_then(OnceCell::take)
+                .flatten();
+        }
+    }
+}
+
 /// A owning reference to a revision.
 ///
 /// Warning: Objects of this type must not be leaked, otherwise all future
@@ -59,6 +134,166 @@ impl fmt::Display for RevisionDetachError {
 // #[cfg(feature = "std")]
 impl std::error::Error for RevisionDetachError {}
 
+/// Error indicating that the queue was closed via [`Queue::close`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueueClosedError;
+
+impl fmt::Display for QueueClosedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "queue is closed")
+    }
+}
+
+// #[cfg(feature = "std")]
+impl std::error::Error for QueueClosedError {}
+
+/// Error indicating a failed publishing attempt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PublishError {
+    /// The queue was closed via [`Queue::close`].
+    Closed,
+
+    /// The queue is [bounded](Queue::bounded) and the slowest
+    /// subscriber lags too far behind.
+    Full,
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishError::Closed => write!(f, "queue is closed"),
+            PublishError::Full => write!(f, "queue is full"),
+        }
+    }
+}
+
+// #[cfg(feature = "std")]
+impl std::error::Error for PublishError {}
+
+impl From<QueueClosedError> for PublishError {
+    #[inline]
+    fn from(_: QueueClosedError) -> Self {
+        PublishError::Closed
+    }
+}
+
+/// Error indicating a failed [`SharedPublisher::publish`] call,
+/// contains the revision which wasn't published.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryPublishError<T> {
+    /// The queue was closed via [`Queue::close`].
+    Closed(T),
+
+    /// The queue is [bounded](Queue::bounded) and the slowest
+    /// subscriber lags too far behind.
+    Full(T),
+}
+
+impl<T> TryPublishError<T> {
+    pub(crate) fn new(e: PublishError, revision: T) -> Self {
+        match e {
+            PublishError::Closed => TryPublishError::Closed(revision),
+            PublishError::Full => TryPublishError::Full(revision),
+        }
+    }
+
+    /// Returns the revision which wasn't published.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        match self {
+            TryPublishError::Closed(x) | TryPublishError::Full(x) => x,
+        }
+    }
+
+    /// Returns the reason why publishing failed.
+    #[inline]
+    pub fn error(&self) -> PublishError {
+        match self {
+            TryPublishError::Closed(_) => PublishError::Closed,
+            TryPublishError::Full(_) => PublishError::Full,
+        }
+    }
+}
+
+impl<T> fmt::Display for TryPublishError<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error(), f)
+    }
+}
+
+// #[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for TryPublishError<T> {}
+
+/// Error indicating a failed [`Queue::try_publish_at_head`] call.
+#[derive(Debug)]
+pub enum ConditionalPublishError<T> {
+    /// Another revision was published before the pending revisions,
+    /// which is consumed by the queue and returned here.
+    Conflict { incoming: RevisionRef<T> },
+
+    /// The queue was closed via [`Queue::close`].
+    Closed,
+
+    /// The queue is [bounded](Queue::bounded) and the slowest
+    /// subscriber lags too far behind.
+    Full,
+}
+
+impl<T> fmt::Display for ConditionalPublishError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConditionalPublishError::Conflict { .. } => {
+                write!(f, "another revision was published concurrently")
+            }
+            ConditionalPublishError::Closed => write!(f, "queue is closed"),
+            ConditionalPublishError::Full => write!(f, "queue is full"),
+        }
+    }
+}
+
+// #[cfg(feature = "std")]
+impl<T: fmt::Debug> std::error::Error for ConditionalPublishError<T> {}
+
+impl<T> From<PublishError> for ConditionalPublishError<T> {
+    #[inline]
+    fn from(e: PublishError) -> Self {
+        match e {
+            PublishError::Closed => ConditionalPublishError::Closed,
+            PublishError::Full => ConditionalPublishError::Full,
+        }
+    }
+}
+
+/// Error indicating that no revision was published in time,
+/// see [`Queue::next_timeout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutError;
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out waiting for a revision")
+    }
+}
+
+// #[cfg(feature = "std")]
+impl std::error::Error for TimeoutError {}
+
+/// Error indicating that a subscriber of a queue with a
+/// [retention limit](Queue::with_retention) lagged too far behind,
+/// contains the number of skipped revisions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "subscriber lagged behind, skipped {} revisions", self.0)
+    }
+}
+
+// #[cfg(feature = "std")]
+impl std::error::Error for Lagged {}
+
 impl<T> Clone for RevisionRef<T> {
     fn clone(&self) -> Self {
         Self {
@@ -74,7 +309,10 @@ impl<T> core::ops::Deref for RevisionRef<T> {
     fn deref(&self) -> &T {
         // This pointer should never change once RevisionRef is created until
         // it's dropped.
-        &unsafe { self.inner.get_unchecked() }.data
+        match unsafe { self.inner.get_unchecked() } {
+            Some(x) => &x.data,
+            None => perfect_unreachable(),
+        }
     }
 }
 
@@ -84,14 +322,42 @@ unsafe impl<T> stable_deref_trait::CloneStableDeref for RevisionRef<T> {}
 impl<T> RevisionRef<T> {
     fn new_and_forward(nr: &mut NextRevision<T>) -> Option<Self> {
         match nr.get() {
-            Some(_) => {
-                let x = Self {
-                    inner: Arc::clone(&nr),
-                };
-                *nr = Arc::clone(&unsafe { nr.get_unchecked() }.next);
-                Some(x)
+            Some(Some(x)) => {
+                let next = Arc::clone(&x.next);
+                Some(Self {
+                    inner: core::mem::replace(nr, next),
+                })
+            }
+            // either nothing published yet, or the chain is terminated
+            _ => None,
+        }
+    }
+
+    /// Returns the cell following this revision and its position.
+    ///
+    /// # Panics
+    /// Panics if this revision doesn't belong to the queue of `shared`.
+    fn following(this: &Self, shared: &Shared) -> (NextRevision<T>, u64) {
+        match this.inner.get() {
+            Some(Some(x)) => {
+                assert!(
+                    x.queue == shared.id,
+                    "revision belongs to a different queue"
+                );
+                (Arc::clone(&x.next), x.seq + 1)
             }
-            None => None,
+            _ => perfect_unreachable(),
+        }
+    }
+
+    /// Returns the sequence number of this revision. Revisions get
+    /// consecutive sequence numbers in the order they were published,
+    /// starting at 0.
+    #[inline]
+    pub fn seq(this: &Self) -> u64 {
+        match this.inner.get() {
+            Some(Some(x)) => x.seq,
+            _ => perfect_unreachable(),
         }
     }
 
@@ -102,16 +368,17 @@ impl<T> RevisionRef<T> {
     /// references have already consumed this revision.
     /// Use this method to reduce queue memory usage if you want to store this
     /// object long-term.
-    pub fn try_detach<'a>(this: &'a mut Self) -> Result<&'a mut T, RevisionDetachError> {
+    pub fn try_detach(this: &mut Self) -> Result<&mut T, RevisionDetachError> {
         // get ownership over the Arc of revision $this.inner
         // (with lifetime = as long as $this.inner exists with the current Arc)
         let mut_this: &mut RevisionNode<T> = Arc::get_mut(&mut this.inner)
             .ok_or(RevisionDetachError)?
             .get_mut()
+            .and_then(Option::as_mut)
             .unwrap();
         // no other reference to *us* exists.
         // override our $next ptr, thus decoupling this node from the following
-        mut_this.next = Arc::new(OnceCell::default());
+        mut_this.next = Link::default();
         Ok(&mut mut_this.data)
     }
 
@@ -120,83 +387,447 @@ impl<T> RevisionRef<T> {
     pub fn try_into_inner(mut this: Self) -> Result<T, Self> {
         // get ownership over the Arc of revision $this.inner
         let mut mut_this: RevisionNode<T> = match Arc::get_mut(&mut this.inner) {
-            Some(x) => x.take().unwrap(),
+            Some(x) => x.take().flatten().unwrap(),
             None => return Err(this),
         };
         // no other reference to *us* exists.
         // override our $next ptr, thus decoupling this node from the following
-        mut_this.next = Arc::new(OnceCell::default());
+        mut_this.next = Link::default();
         Ok(mut_this.data)
     }
 }
 
+// the position of a consuming handle, e.g. the seq of its $next cell
+type Position = Arc<AtomicU64>;
+
+// the last id assigned to a queue, see Shared::id
+static QUEUE_IDS: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug)]
+struct Shared {
+    // identifies the queue, see RevisionNode::queue
+    id: u64,
+
+    // waiting next... calls, keyed by the end of the chain they wait for
+    next_ops: Waiters,
+
+    // publish... calls of bounded queues waiting for free capacity,
+    // keyed by the position the slowest subscriber has to reach
+    space_ops: Waiters,
+
+    // publish... calls of bounded queue instances waiting for free capacity,
+    // keyed like $space_ops, but they ignore their own position, thus
+    // they are notified whenever any subscriber reaches their key
+    own_space_ops: Waiters,
+
+    // publish_and_wait calls, keyed by the position each subscriber
+    // (or the start of the retention window) has to reach
+    ack_ops: Waiters,
+
+    // the maximum lag of the slowest subscriber, if bounded
+    capacity: Option<u64>,
+
+    // the positions of all consuming handles
+    positions: Mutex<Vec<Position>>,
+
+    // the position of the end of the chain, updated after publishing
+    head: AtomicU64,
+
+    // set by Queue::close, before the chain gets terminated
+    closed: AtomicBool,
+
+    // number of handles which are able to publish (Queue, Publisher)
+    publishers: AtomicUsize,
+
+    // number of handles which are able to consume (Queue, Subscriber)
+    subscribers: AtomicUsize,
+
+    // the last origin assigned to a queue instance, see RevisionNode::origin
+    origins: AtomicU64,
+}
+
+impl Shared {
+    fn new(capacity: Option<u64>) -> Self {
+        Shared {
+            id: QUEUE_IDS.fetch_add(1, Ordering::Relaxed) + 1,
+            next_ops: Waiters::new(),
+            space_ops: Waiters::new(),
+            own_space_ops: Waiters::new(),
+            ack_ops: Waiters::new(),
+            capacity,
+            positions: Mutex::new(Vec::new()),
+            head: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            publishers: AtomicUsize::new(0),
+            subscribers: AtomicUsize::new(0),
+            origins: AtomicU64::new(0),
+        }
+    }
+
+    fn positions(&self) -> MutexGuard<'_, Vec<Position>> {
+        // the positions are always consistent
+        self.positions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn register(&self, pos: u64) -> Position {
+        let ret = Arc::new(AtomicU64::new(pos));
+        self.positions().push(Arc::clone(&ret));
+        ret
+    }
+
+    fn unregister(&self, pos: &Position) {
+        let mut positions = self.positions();
+        if let Some(idx) = positions.iter().position(|i| Arc::ptr_eq(i, pos)) {
+            positions.swap_remove(idx);
+        }
+        drop(positions);
+        // dropped handles don't need to consume anything anymore; the handle
+        // still holds $pos while its remaining fields are dropped, thus
+        // publish_and_wait can't detect that it's gone otherwise
+        pos.store(u64::MAX, Ordering::SeqCst);
+        self.notify_space();
+        self.own_space_ops.notify_all();
+        self.ack_ops.notify_all();
+    }
+
+    /// Notifies waiting publishers after a subscriber advanced to `pos`,
+    /// which must have been stored with `SeqCst` ordering.
+    #[inline]
+    fn notify_progress(&self, pos: u64) {
+        self.ack_ops.notify(pos);
+        if self.capacity.is_some() {
+            self.notify_space();
+            self.own_space_ops.notify(pos);
+        }
+    }
+
+    /// Notifies waiting publishers after the retention window moved to
+    /// `start`, which lagging subscribers skip to.
+    #[inline]
+    fn notify_evicted(&self, start: u64) {
+        self.ack_ops.notify(start);
+    }
+
+    /// Notifies the publishers of a bounded queue which wait for the
+    /// slowest subscriber to reach its current position.
+    fn notify_space(&self) {
+        if self.space_ops.is_waiting() {
+            self.space_ops
+                .notify(self.slowest(None).unwrap_or(u64::MAX));
+        }
+    }
+
+    /// Returns the position of the slowest subscriber, if any,
+    /// ignoring the position `except`.
+    fn slowest(&self, except: Option<&Position>) -> Option<u64> {
+        self.positions()
+            .iter()
+            .filter(|i| !matches!(except, Some(except) if Arc::ptr_eq(i, except)))
+            .map(|i| i.load(Ordering::SeqCst))
+            .min()
+    }
+
+    /// Returns how many revisions may be published at the cell with
+    /// position `cell_pos`, which should be the end of the chain.
+    /// The position `own` of the publishing queue instance is ignored,
+    /// otherwise it would wait for itself.
+    fn free_capacity(&self, cell_pos: u64, own: Option<&Position>) -> usize {
+        let capacity = match self.capacity {
+            Some(x) => x,
+            None => return usize::MAX,
+        };
+        let lag = self
+            .slowest(own)
+            .map(|i| cell_pos.saturating_sub(i))
+            .unwrap_or(0);
+        usize::try_from(capacity.saturating_sub(lag)).unwrap_or(usize::MAX)
+    }
+
+    /// Starts listening for free capacity, after publishing at the cell
+    /// with position `cell_pos` failed because the queue was full.
+    /// `own` must be set if the publishing handle is a queue instance,
+    /// see [`free_capacity`](Shared::free_capacity).
+    fn listen_space(&self, cell_pos: u64, own: bool) -> EventListener {
+        // at least one revision fits if the lag is below the capacity
+        let capacity = self.capacity.unwrap_or(0);
+        let ops = if own {
+            &self.own_space_ops
+        } else {
+            &self.space_ops
+        };
+        ops.listen((cell_pos + 1).saturating_sub(capacity))
+    }
+
+    /// Unregisters a publisher. If at most one publisher remains, it
+    /// and all subscribers get notified, because they might now be the last one.
+    fn drop_publisher(&self) {
+        if self.publishers.fetch_sub(1, Ordering::SeqCst) <= 2 {
+            self.next_ops.notify_all();
+        }
+    }
+}
+
 /// A simple event / revision queue
 #[derive(Debug)]
 #[must_use = "Queue does nothing unless you call .next() or some variation of it"]
-pub struct Queue<T> {
+pub struct Queue<T, P = Vec<T>> {
     // the $next field is partially shared, e.g. all queues derived from the same
     // original queue can find the current $next value, but may be a bit behind
     // (e.g. have unconsumed revisions,
     //  which should be iterated to get the current value)
-    next: NextRevision<T>,
+    next: Next<T>,
+
+    // the pending revisions, and where to publish them; if this queue has
+    // a retention limit, $next is only weakly referenced while it is idle
+    outbox: Outbox<T, P>,
+
+    // the revisions which were published by this queue, but not skipped yet
+    own: OwnRevisions,
+
+    // identifies the revisions published by this queue, see RevisionNode::origin
+    origin: u64,
+
+    // the end of the revisions which were last published by this queue,
+    // see publish_and_wait
+    published: u64,
 
-    // waiting next... calls
-    next_ops: Arc<Event>,
+    // the number of revisions which were skipped because this queue lagged
+    // behind the retention window, and which weren't reported yet
+    lagged: u64,
 
-    // currently pending revisions
-    pub pending: Vec<T>,
+    // the position of $next, registered in $shared
+    pos: Position,
+
+    // the listener of the currently waiting next_async call / stream poll,
+    // stored here to make polling cancellation-safe
+    listener: Option<EventListener>,
+
+    // false if this queue is the inner part of a Subscriber,
+    // which isn't counted as publisher
+    publisher: bool,
+
+    // see Queue::deliver_own
+    deliver_own: bool,
 }
 
-impl<T> Clone for Queue<T> {
+impl<T, P: PendingBuffer<T>> Clone for Queue<T, P> {
     #[inline]
     fn clone(&self) -> Self {
-        Queue {
-            next: Arc::clone(&self.next),
-            next_ops: Arc::clone(&self.next_ops),
-            pending: Default::default(),
-        }
+        let mut ret = Queue::with_position(
+            self.next.clone(),
+            self.load_pos(),
+            &self.outbox.shared,
+            self.publisher,
+            self.outbox.retention.clone(),
+            &self.outbox.tail,
+        );
+        ret.own = self.own.clone();
+        ret.deliver_own = self.deliver_own;
+        ret
     }
 }
 
-impl<T> Default for Queue<T> {
+impl<T, P: PendingBuffer<T>> Default for Queue<T, P> {
     #[inline]
     fn default() -> Self {
-        Queue {
-            next: Arc::new(Default::default()),
-            next_ops: Arc::new(Default::default()),
-            pending: Default::default(),
-        }
+        Queue::with_shared(Shared::new(None))
     }
 }
 
-impl<T> Drop for Queue<T> {
+impl<T, P> Drop for Queue<T, P> {
     fn drop(&mut self) {
-        if Arc::strong_count(&self.next_ops) == 2 {
-            self.next_ops.notify(1);
+        self.outbox.shared.unregister(&self.pos);
+        self.outbox
+            .shared
+            .subscribers
+            .fetch_sub(1, Ordering::SeqCst);
+        if self.publisher {
+            self.outbox.shared.drop_publisher();
         }
     }
 }
 
-impl<T: Unpin> Unpin for Queue<T> {}
+// the queue never pins any revision data
+impl<T, P> Unpin for Queue<T, P> {}
 
-impl<T> Iterator for Queue<T> {
+impl<T, P: PendingBuffer<T>> Iterator for Queue<T, P> {
     type Item = RevisionRef<T>;
 
     fn next(&mut self) -> Option<RevisionRef<T>> {
-        let orig_pending_len = self.pending.len();
+        self.publish_before_consuming();
+        let ret = self.forward();
+        self.park();
+        ret
+    }
+}
+
+impl<T, P: PendingBuffer<T>> Publish<T, P> for Queue<T, P> {
+    #[inline]
+    fn outbox(&mut self) -> &mut Outbox<T, P> {
+        &mut self.outbox
+    }
 
-        let ret = match self.publish_intern() {
-            None => RevisionRef::new_and_forward(&mut self.next),
-            x => x,
+    #[inline]
+    fn publish_pending(&mut self) -> Result<(), PublishError> {
+        self.try_publish()
+    }
+
+    #[inline]
+    fn subscribes(&self) -> bool {
+        true
+    }
+}
+
+/// The result of [`Queue::publish`].
+#[derive(Debug)]
+pub struct PublishReport<T> {
+    /// The sequence number of the first published revision,
+    /// the published revisions have consecutive sequence numbers.
+    pub first_seq: u64,
+
+    /// The number of published revisions.
+    pub count: usize,
+
+    /// The revisions which were concurrently published by other handles
+    /// while publishing, thus they weren't known to the shared hint where
+    /// the end of the chain is, and precede the published revisions.
+    /// Other revisions this queue didn't consume yet aren't included, use a
+    /// [`bookmark`](Queue::bookmark) taken before publishing to visit them.
+    /// They aren't consumed, this queue receives them afterwards.
+    pub skipped_incoming: Vec<RevisionRef<T>>,
+}
+
+/// Outcome of a single publishing attempt.
+enum Attempt<T> {
+    /// Publishing succeeded. If the queue is bounded, only the
+    /// revisions which fitted into it might have been published.
+    Done,
+
+    /// The CAS failed, contains the revision which was published in the meantime.
+    Incoming(RevisionRef<T>),
+
+    /// Publishing failed, the pending revisions are kept as-is.
+    Failed(PublishError),
+}
+
+/// Tries to publish the pending revisions once, at the cell `next`
+/// with the position `pos`. Both are forwarded if anything happened.
+/// If the queue is bounded and not all pending revisions fit into it,
+/// only the fitting revisions are published if `partial` is set,
+/// otherwise nothing is published. `own` is the position of the
+/// publishing queue instance, if any, see [`Shared::free_capacity`].
+fn publish_intern<T, P: PendingBuffer<T>>(
+    shared: &Shared,
+    next: &mut NextRevision<T>,
+    pos: &mut u64,
+    pending: &mut P,
+    partial: bool,
+    origin: u64,
+    own: Option<&Position>,
+) -> Attempt<T> {
+    if pending.is_empty() {
+        return Attempt::Done;
+    }
+
+    // : try append to the first 'None' ptr in the 'latest' chain
+    // try to append revnode, if CAS succeeds, done, otherwise:
+    // return a RevisionRef for the failed CAS ptr, and the revnode;
+    // set $latest to the next ptr
+
+    let cell_pos = *pos;
+    let mut published = None;
+    let maybe_real_old = next.get_or_try_init(|| {
+        // we are at the end of the chain, thus the capacity check is exact
+        let allowed = shared.free_capacity(cell_pos, own);
+        if allowed == 0 || (!partial && allowed < pending.len()) {
+            return Err(PublishError::Full);
+        }
+        let mut batch = if allowed < pending.len() {
+            let rest = pending.split_off(allowed);
+            core::mem::replace(pending, rest)
+        } else {
+            core::mem::take(pending)
+        };
+        let end = cell_pos + u64::try_from(batch.len()).unwrap();
+        let latest = Arc::new(OnceCell::default());
+        published = Some((Arc::clone(&latest), end));
+
+        // the revisions get consecutive sequence numbers, starting at $cell_pos,
+        // the nodes are built from the last one backwards
+        // unpack the first one, so we get a node not wrapped in a Arc<OnceCell<_>>
+        let mut first = match batch.pop() {
+            Some(data) => RevisionNode {
+                data,
+                seq: end - 1,
+                queue: shared.id,
+                origin,
+                next: Link(latest),
+            },
+            None => perfect_unreachable(),
         };
+        while let Some(data) = batch.pop() {
+            first = RevisionNode {
+                data,
+                seq: first.seq - 1,
+                queue: shared.id,
+                origin,
+                next: Link(Arc::new(OnceCell::from(Some(first)))),
+            };
+        }
+        Ok(Some(first))
+    });
+    match maybe_real_old {
+        Err(e) => Attempt::Failed(e),
+        Ok(_) if published.is_some() => {
+            // CAS / publishing succeeded
+            let (latest, end) = published.unwrap();
+            *next = latest;
+            *pos = end;
+            shared.head.fetch_max(end, Ordering::SeqCst);
+            // the new revisions are visible via the cell, which isn't SeqCst
+            fence(Ordering::SeqCst);
+            shared.next_ops.notify(end);
+            Attempt::Done
+        }
+        // the chain is terminated
+        Ok(None) => Attempt::Failed(PublishError::Closed),
+        Ok(Some(x)) => {
+            // CAS failed
+            // we need to split this assignment to prevent rustc E0502
+            let new_next = Arc::clone(&x.next);
+            *pos += 1;
 
-        // may have published something
-        if orig_pending_len != self.pending.len() {
-            self.next_ops.notify(usize::MAX);
+            // we discovered a new revision, return that
+            Attempt::Incoming(RevisionRef {
+                // This is safe since the cell cannot be changed once it is set.
+                // use the next revision
+                inner: core::mem::replace(next, new_next),
+            })
         }
+    }
+}
 
-        ret
+/// Marks the queue as closed and terminates the chain,
+/// starting the search for its end at `cur`.
+fn close_intern<T>(shared: &Shared, mut cur: NextRevision<T>) -> bool {
+    if shared.closed.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    // walk to the end of the chain and terminate it,
+    // concurrent publishing attempts either succeed before that
+    // (then we continue with the next cell), or fail.
+    while let Some(x) = cur.get_or_init(|| None) {
+        let next = Arc::clone(&x.next);
+        cur = next;
     }
+    fence(Ordering::SeqCst);
+    shared.next_ops.notify_all();
+    shared.space_ops.notify_all();
+    shared.own_space_ops.notify_all();
+    shared.ack_ops.notify_all();
+    true
 }
 
 impl<T> Queue<T> {
@@ -205,116 +836,1044 @@ impl<T> Queue<T> {
         Default::default()
     }
 
-    fn publish_intern(&mut self) -> Option<RevisionRef<T>> {
-        enum State<T> {
-            ToPublish { rest: Vec<T>, last: T },
-            Published { latest: NextRevision<T> },
+    /// Creates a bounded queue. Publishing only succeeds as long as the
+    /// slowest subscriber lags at most `capacity` revisions behind,
+    /// see [`try_publish`](Queue::try_publish) and
+    /// [`publish_async`](Queue::publish_async).
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn bounded(capacity: usize) -> Self {
+        assert!(capacity > 0, "bounded queue capacity must be non-zero");
+        Queue::with_shared(Shared::new(Some(u64::try_from(capacity).unwrap())))
+    }
+
+    /// Creates a queue with a retention limit. Publishing never blocks,
+    /// instead, subscribers which lag more than `limit` revisions behind
+    /// are moved forward to the oldest retained revision, and the skipped
+    /// revisions are freed even if the subscriber is idle.
+    /// The number of skipped revisions is reported by
+    /// [`try_next`](Queue::try_next) and [`try_next_async`](Queue::try_next_async),
+    /// while all other methods skip them silently.
+    ///
+    /// # Panics
+    /// Panics if `limit` is zero.
+    pub fn with_retention(limit: usize) -> Self {
+        assert!(limit > 0, "queue retention limit must be non-zero");
+        let first = Arc::new(Default::default());
+        let retention = Retention::new(Some(u64::try_from(limit).unwrap()), &first);
+        Queue::with_position(
+            Next::Weak(Arc::downgrade(&first)),
+            0,
+            &Arc::new(Shared::new(None)),
+            true,
+            Some(Arc::new(retention)),
+            &Arc::new(Tail::new(first, 0)),
+        )
+    }
+
+    /// Creates a queue which retains all revisions, even if no queue
+    /// instance would be able to consume them anymore, which makes them
+    /// available to late subscribers, see [`subscribe_from`](Queue::subscribe_from).
+    /// Use [`mark_history`](Queue::mark_history) to free older revisions.
+    pub fn with_history() -> Self {
+        let first = Arc::new(Default::default());
+        let retention = Retention::new(None, &first);
+        Queue::with_position(
+            Next::Weak(Arc::downgrade(&first)),
+            0,
+            &Arc::new(Shared::new(None)),
+            true,
+            Some(Arc::new(retention)),
+            &Arc::new(Tail::new(first, 0)),
+        )
+    }
+}
+
+impl<T, P: PendingBuffer<T>> Queue<T, P> {
+    fn with_shared(shared: Shared) -> Self {
+        let first = Arc::new(Default::default());
+        let tail = Arc::new(Tail::new(Arc::clone(&first), 0));
+        Queue::with_position(Next::Strong(first), 0, &Arc::new(shared), true, None, &tail)
+    }
+
+    pub(crate) fn with_position(
+        next: Next<T>,
+        pos: u64,
+        shared: &Arc<Shared>,
+        publisher: bool,
+        retention: Option<Arc<Retention<T>>>,
+        tail: &Arc<Tail<T>>,
+    ) -> Self {
+        if publisher {
+            shared.publishers.fetch_add(1, Ordering::SeqCst);
+        }
+        shared.subscribers.fetch_add(1, Ordering::SeqCst);
+        let next = match (&retention, next) {
+            // start idle
+            (Some(_), Next::Strong(x)) => Next::Weak(Arc::downgrade(&x)),
+            (_, x) => x,
+        };
+        Queue {
+            next,
+            outbox: Outbox::new(tail, shared, Default::default(), retention),
+            own: OwnRevisions::default(),
+            origin: shared.origins.fetch_add(1, Ordering::Relaxed) + 1,
+            published: 0,
+            lagged: 0,
+            pos: shared.register(pos),
+            listener: None,
+            publisher,
+            deliver_own: false,
+        }
+    }
+
+    #[inline]
+    fn load_pos(&self) -> u64 {
+        self.pos.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    fn store_pos(&mut self, pos: u64) {
+        self.pos.store(pos, Ordering::SeqCst);
+        self.outbox.shared.notify_progress(pos);
+    }
+
+    /// Returns the sequence number of the next revision this queue would
+    /// consume, which is the number of revisions consumed or skipped so far
+    /// (including revisions published by this queue itself, once they're reached).
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.load_pos()
+    }
+
+    /// Returns the strong reference to $next. If this queue has a retention
+    /// limit and lagged behind the retention window, it gets moved forward.
+    /// Use [`park`](Queue::park) to release it afterwards.
+    fn cursor(&mut self) -> &mut NextRevision<T> {
+        if let Next::Weak(weak) = &self.next {
+            let retention = match &self.outbox.retention {
+                Some(x) => x,
+                None => perfect_unreachable(),
+            };
+            let pos = self.load_pos();
+            let start = retention.start();
+            let (strong, new_pos) = match weak.upgrade() {
+                // the retention window retains everything after its start
+                Some(x) if pos >= start.1 => (x, pos),
+                _ => (Arc::clone(&start.0), start.1),
+            };
+            drop(start);
+            self.next = Next::Strong(strong);
+            if new_pos != pos {
+                // the own revisions are skipped anyways
+                let own = self.own.count_from(pos) - self.own.count_from(new_pos);
+                self.lagged += new_pos - pos - own;
+                self.store_pos(new_pos);
+            }
+        }
+        if !self.own.is_empty() {
+            self.skip_own();
         }
+        match &mut self.next {
+            Next::Strong(x) => x,
+            Next::Weak(_) => perfect_unreachable(),
+        }
+    }
 
-        let last = self.pending.pop()?;
+    /// Like [`cursor`](Queue::cursor), but for methods which skip
+    /// silently if this queue lagged behind the retention window,
+    /// thus the skipped revisions aren't reported afterwards.
+    fn cursor_silent(&mut self) -> &mut NextRevision<T> {
+        self.cursor();
+        self.lagged = 0;
+        match &mut self.next {
+            Next::Strong(x) => x,
+            Next::Weak(_) => perfect_unreachable(),
+        }
+    }
 
-        // : try append to the first 'None' ptr in the 'latest' chain
-        // try to append revnode, if CAS succeeds, done, otherwise:
-        // return a RevisionRef for the failed CAS ptr, and the revnode;
-        // set $latest to the next ptr
+    /// Skips the revisions which were published by this queue itself,
+    /// if they are next. $next must be a strong reference.
+    fn skip_own(&mut self) {
+        self.walk(|walk| walk.skip_own());
+    }
 
-        let mut state = State::ToPublish {
-            rest: core::mem::take(&mut self.pending),
-            last,
+    /// Walks the chain from $next, which must be a strong reference,
+    /// via `f`, and moves this queue to where the walk stopped.
+    fn walk<R>(&mut self, f: impl FnOnce(&mut Walk<'_, T>) -> R) -> R {
+        let cur = match &self.next {
+            Next::Strong(x) => Arc::clone(x),
+            Next::Weak(_) => perfect_unreachable(),
         };
-        let maybe_real_old = self.next.get_or_init(|| {
-            let latest = Arc::new(OnceCell::default());
-            if let State::ToPublish { rest, last } = core::mem::replace(
-                &mut state,
-                State::Published {
-                    latest: Arc::clone(&latest),
-                },
-            ) {
-                // unpack the last one, so we get a node not wrapped in a Arc<OnceCell<_>>
-                let prela = RevisionNode {
-                    data: last,
-                    next: latest,
-                };
-                rest.into_iter().rev().fold(prela, |next, i| RevisionNode {
-                    data: i,
-                    next: Arc::new(OnceCell::from(next)),
-                })
-            } else {
-                perfect_unreachable()
+        let orig_pos = self.load_pos();
+        let mut walk = Walk::new(cur, orig_pos, &mut self.own);
+        let ret = f(&mut walk);
+        let (cur, pos) = walk.finish();
+        self.next = Next::Strong(cur);
+        if pos != orig_pos {
+            self.store_pos(pos);
+        }
+        ret
+    }
+
+    /// Releases the strong reference to $next if this queue has a retention limit.
+    fn park(&mut self) {
+        if self.outbox.retention.is_some() {
+            if let Next::Strong(x) = &self.next {
+                self.next = Next::Weak(Arc::downgrade(x));
             }
-        });
-        match state {
-            State::Published { latest } => {
-                // CAS / publishing succeeded
-                self.next = latest;
-                None
+        }
+    }
+
+    /// Publishes the pending revisions before anything gets consumed,
+    /// the revisions which were published concurrently are consumed afterwards.
+    fn publish_before_consuming(&mut self) {
+        if !self.outbox.pending.is_empty() {
+            while let (Attempt::Incoming(_), _) = self.publish_intern() {}
+        }
+    }
+
+    /// Consumes up to `max` published revisions into `batch`. Unlike calling
+    /// [`forward`](Queue::forward) repeatedly, the position is only stored
+    /// once, thus waiting publishers are only notified once.
+    fn forward_batch(&mut self, batch: &mut Vec<RevisionRef<T>>, max: usize) {
+        self.cursor_silent();
+        self.walk(|walk| batch.extend(walk.take(max)));
+    }
+
+    /// Consumes the next revision, if any was published.
+    fn forward(&mut self) -> Option<RevisionRef<T>> {
+        let ret = RevisionRef::new_and_forward(self.cursor_silent())?;
+        self.store_pos(self.load_pos() + 1);
+        Some(ret)
+    }
+
+    /// Tries to publish the pending revisions once at the end of the chain,
+    /// which is found via the shared tail hint unless this queue already
+    /// consumed everything, see [`publish_intern`].
+    /// Also returns the position of the cell at which the attempt was made.
+    ///
+    /// Revisions which were published in the meantime aren't consumed,
+    /// the revisions published by this queue are skipped when they are
+    /// reached, unless it [delivers its own revisions](Queue::deliver_own).
+    fn publish_intern(&mut self) -> (Attempt<T>, u64) {
+        let (mut tail, mut pos) = match &self.next {
+            // $next is the end of the chain, thus the hint isn't needed
+            Next::Strong(x) if x.get().is_none() => (Arc::clone(x), self.load_pos()),
+            _ => self.outbox.tail.get(),
+        };
+        let cell_pos = pos;
+        let ret = publish_intern(
+            &self.outbox.shared,
+            &mut tail,
+            &mut pos,
+            &mut self.outbox.pending,
+            true,
+            self.origin,
+            Some(&self.pos),
+        );
+        if pos != cell_pos {
+            self.outbox.tail.update(tail, pos);
+            if let Some(retention) = &self.outbox.retention {
+                retention.advance(pos, &self.outbox.shared);
             }
-            State::ToPublish { mut rest, last } => {
-                // CAS failed
-                // we need to split this assignment to prevent rustc E0502
-                let new_next = Arc::clone(&maybe_real_old.next);
-
-                // this publishing failed
-                rest.push(last);
-                self.pending = rest;
-
-                // we discovered a new revision, return that
-                Some(RevisionRef {
-                    // This is safe since the cell cannot be changed once it is set.
-                    // use the next revision
-                    inner: core::mem::replace(&mut self.next, new_next),
-                })
+            if let Attempt::Done = ret {
+                self.published = pos;
+                if !self.deliver_own {
+                    self.own.push(cell_pos, pos);
+                    // skip them right now if nothing is in between
+                    self.cursor();
+                }
             }
         }
+        (ret, cell_pos)
     }
 
-    /// Waits asynchronously for an event to be published on the queue.
-    /// Only returns `None` if no other reference to the queue
-    /// exists anymore, because otherwise nothing could wake this up.
-    /// Tries to publish pending revisions while waiting.
-    pub async fn next_async(&mut self) -> Option<RevisionRef<T>> {
-        let mut listener = None;
+    /// Returns the number of skipped revisions which weren't reported yet.
+    fn take_lagged(&mut self) -> Result<(), Lagged> {
+        match core::mem::take(&mut self.lagged) {
+            0 => Ok(()),
+            n => Err(Lagged(n)),
+        }
+    }
 
+    /// Like [`Iterator::next`], but reports if this queue lagged behind
+    /// the retention window, see [`with_retention`](Queue::with_retention).
+    /// The revision after the skipped revisions is returned by the next call.
+    pub fn try_next(&mut self) -> Result<Option<RevisionRef<T>>, Lagged> {
+        self.cursor();
+        if let Err(e) = self.take_lagged() {
+            self.park();
+            return Err(e);
+        }
+        Ok(self.next())
+    }
+
+    /// Polls for the next revision, see [`next_async`](Queue::next_async).
+    /// The listener used for waiting is stored inside the queue,
+    /// thus dropping a pending poll doesn't lose any revision.
+    pub fn poll_next_revision(&mut self, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
         loop {
-            if let ret @ Some(_) = self.next() {
+            match self.poll_try_next(cx) {
+                Poll::Ready(Ok(x)) => return Poll::Ready(x),
+                // skip silently
+                Poll::Ready(Err(Lagged(_))) => {}
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Polls for the next revision, see [`try_next_async`](Queue::try_next_async).
+    pub fn poll_try_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<RevisionRef<T>>, Lagged>> {
+        loop {
+            self.cursor();
+            if let Err(e) = self.take_lagged() {
+                self.park();
+                self.listener = None;
+                return Poll::Ready(Err(e));
+            } else if let ret @ Some(_) = self.next() {
                 // we got something, return
-                // notify another blocked receive operation
-                self.next_ops.notify(1);
-                return ret;
-            } else if Arc::get_mut(&mut self.next_ops).is_some() {
-                // cancel if no one is listening
+                // other blocked receive operations were already notified by
+                // the publisher, and don't need to be woken again
+                self.listener = None;
+                return Poll::Ready(Ok(ret));
+            } else if self.is_terminated() || !self.others_can_publish() {
+                // the queue was closed and we consumed all revisions, or
+                // cancel if no one else could publish anything
                 // skip publishing + notifying phase bc no one is listening
                 // we need to re-check to catch a race-condition between
-                // the call to $self.next and the check of $self.next_ops
+                // the call to $self.next and the check of $self.outbox.shared
                 // in between other queue instances may have been destroyed,
                 // but messages are still in the queue.
-                return RevisionRef::new_and_forward(&mut self.next);
+                self.listener = None;
+                let ret = self.forward();
+                self.park();
+                return Poll::Ready(Ok(ret));
             } else {
-                match listener.take() {
+                match &mut self.listener {
                     None => {
                         // Start listening and then try receiving again.
-                        listener = Some(self.next_ops.listen());
+                        self.listener =
+                            Some(self.outbox.shared.next_ops.listen(self.load_pos() + 1));
                     }
                     Some(l) => {
                         // Wait for a notification.
-                        l.await;
+                        match Pin::new(l).poll(cx) {
+                            Poll::Ready(()) => self.listener = None,
+                            Poll::Pending => return Poll::Pending,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the next revision without consuming it, if any was published.
+    /// Unlike [`Iterator::next`], pending revisions aren't published.
+    /// Use [`advance`](Queue::advance) to consume it afterwards.
+    pub fn peek(&mut self) -> Option<RevisionRef<T>> {
+        let cur = self.cursor_silent();
+        let ret = match cur.get() {
+            Some(Some(_)) => Some(RevisionRef {
+                inner: Arc::clone(cur),
+            }),
+            _ => None,
+        };
+        self.park();
+        ret
+    }
+
+    /// Returns a [`Cursor`] at the position of this queue, which can be used
+    /// to re-read the following revisions, or to move this queue (or another
+    /// instance of it) back to this position via [`rewind_to`](Queue::rewind_to).
+    pub fn bookmark(&mut self) -> Cursor<T> {
+        let next = Arc::clone(self.cursor());
+        self.park();
+        Cursor {
+            next,
+            pos: self.load_pos(),
+            shared: Arc::clone(&self.outbox.shared),
+        }
+    }
+
+    /// Moves this queue to the position of `cursor`, thus the revisions
+    /// following it are consumed again. If this queue has a
+    /// [retention limit](Queue::with_retention) and the position is already
+    /// outside the retention window, this queue lags behind.
+    ///
+    /// The revisions this queue published itself are skipped again,
+    /// unless it [delivers its own revisions](Queue::deliver_own).
+    ///
+    /// # Panics
+    /// Panics if `cursor` wasn't created from an instance of this queue.
+    pub fn rewind_to(&mut self, cursor: &Cursor<T>) {
+        assert!(
+            Arc::ptr_eq(&self.outbox.shared, &cursor.shared),
+            "cursor belongs to a different queue"
+        );
+        self.next = Next::Strong(Arc::clone(&cursor.next));
+        self.store_pos(cursor.pos);
+        // the revisions following the cursor are read again anyways
+        self.own.clear();
+        if !self.deliver_own {
+            let mut cur = &cursor.next;
+            while let Some(Some(x)) = cur.get() {
+                if x.origin == self.origin {
+                    self.own.push(x.seq, x.seq + 1);
+                }
+                cur = &x.next;
+            }
+        }
+        self.park();
+    }
+
+    /// Consumes the next revision without returning it, if any was published.
+    /// Returns `false` if there was nothing to consume.
+    /// Unlike [`Iterator::next`], pending revisions aren't published.
+    pub fn advance(&mut self) -> bool {
+        let ret = self.forward().is_some();
+        self.park();
+        ret
+    }
+
+    /// Consumes all published revisions without returning them, and
+    /// returns how many revisions were skipped, including revisions which
+    /// were skipped because this queue lagged behind the retention window.
+    /// Pending revisions aren't published.
+    pub fn skip_to_head(&mut self) -> u64 {
+        self.cursor();
+        let lagged = self.lagged;
+        let (_, skipped) = self.forward_to_head();
+        skipped + lagged
+    }
+
+    /// Consumes all published revisions, and returns the newest one.
+    /// Like the iterator, this skips the revisions this queue published
+    /// itself, unless it [delivers its own revisions](Queue::deliver_own),
+    /// thus an older revision is returned if the newest ones are own ones.
+    /// Pending revisions aren't published.
+    pub fn latest(&mut self) -> Option<RevisionRef<T>> {
+        self.forward_to_head().0
+    }
+
+    /// Moves this queue to the end of the chain, returns the last
+    /// consumed revision and the number of consumed revisions.
+    fn forward_to_head(&mut self) -> (Option<RevisionRef<T>>, u64) {
+        self.cursor_silent();
+        let ret = self.walk(|walk| walk.fold((None, 0), |(_, n), i| (Some(i), n + 1)));
+        self.own.clear();
+        self.park();
+        ret
+    }
+
+    /// Polls for the next revision without consuming it,
+    /// see [`peek_async`](Queue::peek_async).
+    pub fn poll_peek(&mut self, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
+        loop {
+            if let ret @ Some(_) = self.peek() {
+                self.listener = None;
+                return Poll::Ready(ret);
+            } else if self.is_terminated() || !self.others_can_publish() {
+                // re-check, see poll_try_next
+                self.listener = None;
+                return Poll::Ready(self.peek());
+            }
+            match &mut self.listener {
+                None => {
+                    self.listener = Some(self.outbox.shared.next_ops.listen(self.load_pos() + 1))
+                }
+                Some(l) => match Pin::new(l).poll(cx) {
+                    Poll::Ready(()) => self.listener = None,
+                    Poll::Pending => return Poll::Pending,
+                },
+            }
+        }
+    }
+
+    /// Waits asynchronously for the next revision without consuming it.
+    /// Returns `None` in the same cases as [`next_async`](Queue::next_async),
+    /// but doesn't publish pending revisions while waiting.
+    pub async fn peek_async(&mut self) -> Option<RevisionRef<T>> {
+        core::future::poll_fn(|cx| self.poll_peek(cx)).await
+    }
+
+    /// Waits asynchronously for an event to be published on the queue.
+    /// Only returns `None` if the queue was [closed](Queue::close) and all
+    /// remaining revisions were consumed, or if no other queue or publisher
+    /// exists anymore, because otherwise nothing could wake this up.
+    /// Tries to publish pending revisions while waiting.
+    pub async fn next_async(&mut self) -> Option<RevisionRef<T>> {
+        core::future::poll_fn(|cx| self.poll_next_revision(cx)).await
+    }
+
+    /// Returns up to `max` published revisions at once, like calling
+    /// [`Iterator::next`] repeatedly. Never waits.
+    pub fn next_batch(&mut self, max: usize) -> Vec<RevisionRef<T>> {
+        self.publish_before_consuming();
+        let mut ret = Vec::new();
+        self.forward_batch(&mut ret, max);
+        self.park();
+        ret
+    }
+
+    /// Polls for a batch of revisions, see [`next_batch_async`](Queue::next_batch_async).
+    pub fn poll_next_batch(
+        &mut self,
+        cx: &mut Context<'_>,
+        max: usize,
+    ) -> Poll<Vec<RevisionRef<T>>> {
+        if max == 0 {
+            return Poll::Ready(Vec::new());
+        }
+        self.poll_next_revision(cx).map(|first| match first {
+            Some(first) => {
+                let mut ret = alloc::vec![first];
+                self.forward_batch(&mut ret, max - 1);
+                self.park();
+                ret
+            }
+            None => Vec::new(),
+        })
+    }
+
+    /// Waits asynchronously until any revision was published, like
+    /// [`next_async`](Queue::next_async), and then returns up to `max`
+    /// published revisions at once. Only returns an empty batch in the
+    /// cases in which `next_async` returns `None`, or if `max` is zero.
+    pub async fn next_batch_async(&mut self, max: usize) -> Vec<RevisionRef<T>> {
+        core::future::poll_fn(|cx| self.poll_next_batch(cx, max)).await
+    }
+
+    /// Returns an iterator over the events of the consumed revisions,
+    /// for revisions which contain multiple events (e.g. `Queue<Vec<E>>`).
+    /// The events are borrowed from the revisions, see [`EventRef`],
+    /// use [`Events::next_async`] to wait for events asynchronously.
+    ///
+    /// Only revisions which can be viewed as a slice of events are supported
+    /// (`T: AsRef<[E]>`, e.g. `Vec<E>`, `Box<[E]>` or arrays), other
+    /// containers like `VecDeque<E>` have to be converted before publishing.
+    #[inline]
+    pub fn events<E>(&mut self) -> Events<'_, T, E, P>
+    where
+        T: AsRef<[E]>,
+    {
+        Events::new(self)
+    }
+
+    /// Blocks the current thread until an event is published on the queue,
+    /// like [`next_async`](Queue::next_async).
+    pub fn next_blocking(&mut self) -> Option<RevisionRef<T>> {
+        match self.wait_next(None) {
+            Ok(x) => x,
+            Err(TimeoutError) => perfect_unreachable(),
+        }
+    }
+
+    /// Like [`next_blocking`](Queue::next_blocking), but fails
+    /// if nothing was published within `timeout`.
+    pub fn next_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<RevisionRef<T>>, TimeoutError> {
+        // an unrepresentable deadline is never reached
+        self.wait_next(Instant::now().checked_add(timeout))
+    }
+
+    /// Like [`next_blocking`](Queue::next_blocking), but fails
+    /// if nothing was published until `deadline`.
+    #[inline]
+    pub fn next_deadline(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Option<RevisionRef<T>>, TimeoutError> {
+        self.wait_next(Some(deadline))
+    }
+
+    /// The blocking equivalent of [`poll_next_revision`](Queue::poll_next_revision).
+    fn wait_next(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Option<RevisionRef<T>>, TimeoutError> {
+        let mut listener = None;
+
+        loop {
+            // skips silently
+            if let ret @ Some(_) = self.next() {
+                return Ok(ret);
+            } else if self.is_terminated() || !self.others_can_publish() {
+                // see poll_try_next
+                let ret = self.forward();
+                self.park();
+                return Ok(ret);
+            }
+            match (listener.take(), deadline) {
+                (None, _) => {
+                    listener = Some(self.outbox.shared.next_ops.listen(self.load_pos() + 1))
+                }
+                (Some(l), None) => l.wait(),
+                (Some(l), Some(deadline)) => {
+                    if !l.wait_deadline(deadline) {
+                        return Err(TimeoutError);
                     }
                 }
             }
         }
     }
 
+    /// Like [`next_async`](Queue::next_async), but reports if this queue
+    /// lagged behind the retention window, see [`try_next`](Queue::try_next).
+    pub async fn try_next_async(&mut self) -> Result<Option<RevisionRef<T>>, Lagged> {
+        core::future::poll_fn(|cx| self.poll_try_next(cx)).await
+    }
+
+    /// Publishes the pending revisions at once, and reports which revisions
+    /// were published, and which revisions were concurrently published by
+    /// other handles, see [`PublishReport::skipped_incoming`].
+    /// This doesn't depend on the number of unconsumed revisions.
+    /// If the queue is [bounded](Queue::bounded), as many pending revisions
+    /// as possible are published, and the rest is kept.
+    ///
+    /// Fails if nothing could be published.
+    pub fn publish(&mut self) -> Result<PublishReport<T>, PublishError> {
+        let orig_pending_len = self.outbox.pending.len();
+        let mut skipped_incoming = Vec::new();
+        let ret = loop {
+            match self.publish_intern() {
+                (Attempt::Done, first_seq) => {
+                    break Ok(PublishReport {
+                        first_seq,
+                        count: orig_pending_len - self.outbox.pending.len(),
+                        skipped_incoming,
+                    })
+                }
+                (Attempt::Incoming(x), _) => skipped_incoming.push(x),
+                (Attempt::Failed(e), _) => break Err(e),
+            }
+        };
+        self.park();
+        ret
+    }
+
+    /// Sets whether this queue instance receives the revisions it publishes
+    /// itself (default: `false`). If enabled, this queue receives all
+    /// revisions in the order they were published, including its own.
+    /// This setting is inherited by [clones](Clone::clone) of this queue.
+    #[inline]
+    pub fn deliver_own(&mut self, deliver_own: bool) {
+        self.deliver_own = deliver_own;
+    }
+
+    /// Publishes the pending revisions only if no other revision was published
+    /// after the revisions this queue already consumed, and returns the
+    /// sequence number of the first published revision. Otherwise, the
+    /// pending revisions are kept, and the first revision which was published
+    /// in the meantime is consumed and returned as a conflict, in which case
+    /// the remaining revisions should be consumed before retrying.
+    /// If the queue is [bounded](Queue::bounded) and not all pending revisions
+    /// fit into it, nothing is published and `Full` is returned.
+    pub fn try_publish_at_head(&mut self) -> Result<u64, ConditionalPublishError<T>> {
+        // resolve the cursor first, it might move us forward
+        let mut next = Arc::clone(self.cursor());
+        let first_seq = self.load_pos();
+        let mut pos = first_seq;
+        let ret = match publish_intern(
+            &self.outbox.shared,
+            &mut next,
+            &mut pos,
+            &mut self.outbox.pending,
+            false,
+            self.origin,
+            Some(&self.pos),
+        ) {
+            Attempt::Done => Ok(first_seq),
+            Attempt::Incoming(incoming) => Err(ConditionalPublishError::Conflict { incoming }),
+            Attempt::Failed(e) => Err(e.into()),
+        };
+        if pos != first_seq {
+            if let Some(retention) = &self.outbox.retention {
+                retention.advance(pos, &self.outbox.shared);
+            }
+            if ret.is_ok() {
+                self.outbox.tail.update(Arc::clone(&next), pos);
+                self.published = pos;
+            }
+            if !(self.deliver_own && ret.is_ok()) {
+                self.next = Next::Strong(next);
+                self.store_pos(pos);
+            }
+        }
+        self.park();
+        ret
+    }
+
+    /// Publishes all pending revisions after any revisions which were
+    /// concurrently published by other queue instances, which this queue
+    /// receives afterwards.
+    /// If the queue is [bounded](Queue::bounded), as many pending revisions
+    /// as possible are published, and the rest is kept. The revisions this
+    /// queue instance didn't consume yet don't count while it publishes,
+    /// but they limit other publishers like those of any other subscriber.
+    pub fn try_publish(&mut self) -> Result<(), PublishError> {
+        let ret = loop {
+            match self.publish_intern().0 {
+                Attempt::Done if self.outbox.pending.is_empty() => break Ok(()),
+                Attempt::Done | Attempt::Incoming(_) => {}
+                Attempt::Failed(e) => break Err(e),
+            }
+        };
+        self.park();
+        ret
+    }
+
+    /// Polls for publishing all pending revisions,
+    /// see [`publish_async`](Queue::publish_async).
+    #[inline]
+    pub fn poll_publish(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), QueueClosedError>> {
+        outbox::poll_publish(self, cx)
+    }
+
+    /// Publishes all pending revisions like [`try_publish`](Queue::try_publish),
+    /// but if the queue is [bounded](Queue::bounded), waits asynchronously
+    /// until the slowest subscriber caught up enough.
+    pub async fn publish_async(&mut self) -> Result<(), QueueClosedError> {
+        core::future::poll_fn(|cx| self.poll_publish(cx)).await
+    }
+
+    /// Publishes all pending revisions like [`publish_async`](Queue::publish_async),
+    /// and then waits asynchronously until every other queue instance and
+    /// subscriber which existed when this method was called consumed them,
+    /// or was dropped. Queue instances which lagged behind the
+    /// [retention window](Queue::with_retention) beyond these revisions
+    /// don't receive them anymore, thus they aren't waited for.
+    /// Returns immediately if nothing is pending.
+    pub async fn publish_and_wait(&mut self) -> Result<(), QueueClosedError> {
+        if self.outbox.pending.is_empty() {
+            return Ok(());
+        }
+        let mut waiting: Vec<_> = self
+            .outbox
+            .shared
+            .positions()
+            .iter()
+            .filter(|i| !Arc::ptr_eq(i, &self.pos))
+            .cloned()
+            .collect();
+        self.publish_async().await?;
+        let end = self.published;
+        let mut listener = None;
+
+        loop {
+            // lagging queue instances are moved forward to the window start
+            let start = self.outbox.retention.as_ref().map_or(0, |i| i.start().1);
+            // the positions of dropped handles are moved to the end, see unregister
+            waiting.retain(|i| i.load(Ordering::SeqCst).max(start) < end);
+            if waiting.is_empty() {
+                return Ok(());
+            }
+            match listener.take() {
+                None => listener = Some(self.outbox.shared.ack_ops.listen(end)),
+                Some(l) => l.await,
+            }
+        }
+    }
+
     /// This method enqueues the pending revision for publishing.
-    /// The iterator **must** be "collected"/"polled"
-    /// (calling [`Iterator::next`] until it returns None) to publish them.
+    /// It is published by the next call to [`publish`](Queue::publish),
+    /// [`try_publish`](Queue::try_publish) or one of their variations,
+    /// or when the next revision is consumed via [`Iterator::next`].
+    /// Fails if the queue was [closed](Queue::close).
     #[inline(always)]
-    pub fn enqueue(&mut self, pending: T) {
-        self.pending.push(pending);
+    pub fn enqueue(&mut self, pending: T) -> Result<(), QueueClosedError> {
+        self.outbox.enqueue(pending)
+    }
+
+    /// Enqueues multiple revisions for publishing at once,
+    /// see [`enqueue`](Queue::enqueue).
+    #[inline]
+    pub fn enqueue_many<I>(&mut self, pending: I) -> Result<(), QueueClosedError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.outbox.enqueue_many(pending)
+    }
+
+    /// Returns the revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn pending(&self) -> &[T] {
+        self.outbox.pending.as_slice()
+    }
+
+    /// Returns the number of revisions which were enqueued,
+    /// but not published yet.
+    #[inline]
+    pub fn pending_len(&self) -> usize {
+        self.outbox.pending.len()
+    }
+
+    /// Takes back all revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn retract_pending(&mut self) -> P {
+        core::mem::take(&mut self.outbox.pending)
+    }
+
+    /// Discards all revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn clear_pending(&mut self) {
+        self.outbox.pending.clear();
+    }
+
+    /// Converts this queue instance into one which stores its pending
+    /// revisions in a container of type `Q` (default: `Vec<T>`),
+    /// the currently pending revisions are moved into it.
+    /// The container is inherited by [clones](Clone::clone) and
+    /// [publishers](Queue::split) derived from the new instance.
+    pub fn with_pending_buffer<Q: PendingBuffer<T>>(mut self) -> Queue<T, Q> {
+        let mut ret: Queue<T, Q> = Queue::with_position(
+            self.next.clone(),
+            self.load_pos(),
+            &self.outbox.shared,
+            self.publisher,
+            self.outbox.retention.clone(),
+            &self.outbox.tail,
+        );
+        ret.own = core::mem::take(&mut self.own);
+        ret.origin = self.origin;
+        ret.published = self.published;
+        ret.deliver_own = self.deliver_own;
+        ret.lagged = self.lagged;
+        self.outbox.pending.drain_into(&mut ret.outbox.pending);
+        ret
+    }
+
+    /// Closes the queue, which terminates the revision chain.
+    /// All queue instances are able to consume the remaining revisions,
+    /// after that, [`next_async`](Queue::next_async) returns `None`.
+    /// Revisions which weren't published yet can't be published anymore,
+    /// and further calls to [`enqueue`](Queue::enqueue) fail.
+    ///
+    /// Returns `false` if the queue was already closed.
+    #[inline]
+    pub fn close(&self) -> bool {
+        close_intern(&self.outbox.shared, self.outbox.tail.get().0)
+    }
+
+    /// Returns `true` if the queue was [closed](Queue::close).
+    /// Note that there might still be unconsumed revisions.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.outbox.is_closed()
+    }
+
+    /// Returns the number of published revisions which this queue
+    /// didn't consume yet. Revisions which were evicted from the
+    /// [retention window](Queue::with_retention) aren't counted.
+    pub fn available(&self) -> u64 {
+        let mut pos = self.load_pos();
+        if let Some(retention) = &self.outbox.retention {
+            pos = pos.max(retention.start().1);
+        }
+        let own = self.own.count_from(pos);
+        self.outbox
+            .shared
+            .head
+            .load(Ordering::SeqCst)
+            .saturating_sub(pos)
+            .saturating_sub(own)
+    }
+
+    /// Returns the number of handles which are able to consume revisions
+    /// (queues and subscribers), including this one.
+    #[inline]
+    pub fn subscriber_count(&self) -> usize {
+        self.outbox.shared.subscribers.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of handles which are able to publish revisions
+    /// (queues and publishers), including this one.
+    #[inline]
+    pub fn publisher_count(&self) -> usize {
+        self.outbox.shared.publishers.load(Ordering::SeqCst)
+    }
+
+    /// Returns `true` if any other handle is able to publish revisions.
+    #[inline]
+    fn others_can_publish(&self) -> bool {
+        self.outbox.shared.publishers.load(Ordering::SeqCst) > usize::from(self.publisher)
+    }
+
+    /// Splits this queue into a [`Publisher`], which takes over the pending
+    /// revisions, and a [`Subscriber`], which takes over the position.
+    pub fn split(mut self) -> (Publisher<T, P>, Subscriber<T>) {
+        let pending = core::mem::take(&mut self.outbox.pending);
+        let retention = self.outbox.retention.clone();
+        let publ = Publisher::new(&self.outbox.tail, &self.outbox.shared, pending, retention);
+        let sub = Subscriber::from_queue(&mut self);
+        (publ, sub)
+    }
+
+    /// Creates a new queue instance, which starts at the given point
+    /// of the retained history, unlike [`clone`](Clone::clone),
+    /// which starts at the position of this queue.
+    ///
+    /// Only queues created via [`with_retention`](Queue::with_retention) or
+    /// [`with_history`](Queue::with_history) retain revisions for replay,
+    /// otherwise the oldest available revision is the next revision
+    /// this queue would consume.
+    pub fn subscribe_from(&self, replay: Replay) -> Self {
+        let (next, pos) = self.replay_start(replay);
+        Queue::with_position(
+            Next::Strong(next),
+            pos,
+            &self.outbox.shared,
+            true,
+            self.outbox.retention.clone(),
+            &self.outbox.tail,
+        )
+    }
+
+    /// Creates a new queue instance, which starts right after `revision`,
+    /// e.g. with the revision following it. Useful to resume processing
+    /// after the last processed revision.
+    ///
+    /// # Panics
+    /// Panics if `revision` wasn't published on this queue.
+    pub fn resume_after(&self, revision: &RevisionRef<T>) -> Self {
+        let (next, pos) = RevisionRef::following(revision, &self.outbox.shared);
+        Queue::with_position(
+            Next::Strong(next),
+            pos,
+            &self.outbox.shared,
+            true,
+            self.outbox.retention.clone(),
+            &self.outbox.tail,
+        )
+    }
+
+    /// Frees all retained revisions which this queue already consumed,
+    /// thus they aren't available to [`subscribe_from`](Queue::subscribe_from)
+    /// anymore, and other queue instances which lag behind are moved forward.
+    /// Does nothing if this queue doesn't retain any revisions.
+    pub fn mark_history(&mut self) {
+        let retention = match &self.outbox.retention {
+            Some(x) => Arc::clone(x),
+            None => return,
+        };
+        let cur = Arc::clone(self.cursor());
+        retention.mark(&cur, self.load_pos(), &self.outbox.shared);
+        self.park();
+    }
+
+    /// Finds the cell and position of a new subscription.
+    fn replay_start(&self, replay: Replay) -> (NextRevision<T>, u64) {
+        let (mut cur, mut pos) = match &self.outbox.retention {
+            Some(retention) => {
+                let start = retention.start();
+                (Arc::clone(&start.0), start.1)
+            }
+            None => (self.next_strong(), self.load_pos()),
+        };
+        let skip = match replay {
+            Replay::Head => u64::MAX,
+            Replay::LastN(n) => {
+                let head = self.outbox.shared.head.load(Ordering::SeqCst);
+                head.saturating_sub(u64::try_from(n).unwrap_or(u64::MAX))
+                    .saturating_sub(pos)
+            }
+            Replay::All => 0,
+        };
+        for _ in 0..skip {
+            let next = match cur.get() {
+                Some(Some(x)) => Arc::clone(&x.next),
+                _ => break,
+            };
+            cur = next;
+            pos += 1;
+        }
+        (cur, pos)
+    }
+
+    /// Creates a [`SharedPublisher`] for this queue, which
+    /// starts publishing at the end of the revision chain.
+    pub fn shared_publisher(&self) -> SharedPublisher<T> {
+        SharedPublisher::new(
+            &self.outbox.tail,
+            &self.outbox.shared,
+            self.outbox.retention.clone(),
+        )
+    }
+
+    /// Returns a strong reference to $next without changing the state
+    /// of this queue. If this queue lagged behind the retention window,
+    /// the start of the window is returned instead.
+    fn next_strong(&self) -> NextRevision<T> {
+        match (self.next.upgrade(), &self.outbox.retention) {
+            (Some(x), _) => x,
+            (None, Some(retention)) => Arc::clone(&retention.start().0),
+            (None, None) => perfect_unreachable(),
+        }
+    }
+
+    /// Returns `true` if this queue instance reached the end of
+    /// a terminated revision chain.
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        match self.next.upgrade() {
+            Some(x) => matches!(x.get(), Some(None)),
+            // lagged behind, there are retained revisions
+            None => false,
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T, P: PendingBuffer<T>> futures_core::Stream for Queue<T, P> {
+    type Item = RevisionRef<T>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
+        self.get_mut().poll_next_revision(cx)
+    }
+}
+
+/// Publishing sink; [`poll_flush`](futures_sink::Sink::poll_flush) publishes
+/// all pending revisions, see [`Queue::try_publish`].
+/// If the queue is [bounded](Queue::bounded), `poll_ready` waits until
+/// all previously sent revisions were published.
+///
+/// Note that the queue instance keeps all revisions published by other
+/// handles for its own consumption, use the [`Publisher`] of a
+/// [split](Queue::split) queue as sink if nothing is consumed from it.
+#[cfg(feature = "sink")]
+impl<T, P: PendingBuffer<T>> futures_sink::Sink<T> for Queue<T, P> {
+    type Error = QueueClosedError;
+
+    #[inline]
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        outbox::poll_ready(self.get_mut(), cx)
+    }
+
+    #[inline]
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.get_mut().enqueue(item)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_publish(cx)
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
     }
 }
 
 // #[cfg(feature = "std")]
-impl<T: std::fmt::Debug> Queue<T> {
+impl<T: std::fmt::Debug, P: PendingBuffer<T>> Queue<T, P> {
     /// Helper function, prints all unprocessed, newly published revisions
     #[cold]
     pub fn print_debug<W: std::io::Write>(
@@ -322,7 +1881,7 @@ impl<T: std::fmt::Debug> Queue<T> {
         mut writer: W,
         prefix: &str,
     ) -> std::io::Result<()> {
-        let mut cur = Arc::clone(&self.next);
+        let mut cur = self.next_strong();
         let mut fi = true;
         let mut tmpstr = String::new();
         while let Some(x) = RevisionRef::new_and_forward(&mut cur) {
@@ -335,11 +1894,13 @@ impl<T: std::fmt::Debug> Queue<T> {
         }
         writeln!(
             writer,
-            "{} [{}] pending = {:?}; next_ops x{}",
+            "{} [{}] pending = {:?}; subscribers = {}; publishers = {}; closed = {}",
             prefix,
             tmpstr,
-            &self.pending,
-            Arc::strong_count(&self.next_ops)
+            self.outbox.pending.as_slice(),
+            self.subscriber_count(),
+            self.publisher_count(),
+            self.is_closed()
         )?;
         Ok(())
     }
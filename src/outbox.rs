@@ -0,0 +1,196 @@
+use crate::{
+    publish_intern, retention::Retention, tail::Tail, Attempt, PendingBuffer, PublishError,
+    QueueClosedError, Shared,
+};
+use alloc::sync::Arc;
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::Ordering,
+    task::{Context, Poll},
+};
+use event_listener::EventListener;
+
+/// The state which is needed to publish revisions,
+/// shared by the implementations of `Queue` and `Publisher`.
+#[derive(Debug)]
+pub(crate) struct Outbox<T, P> {
+    // the end of the chain, shared between all handles of the queue
+    pub(crate) tail: Arc<Tail<T>>,
+
+    // state shared between all handles of the queue
+    pub(crate) shared: Arc<Shared>,
+
+    // currently pending revisions
+    pub(crate) pending: P,
+
+    // the retention window, if the queue has a retention limit
+    pub(crate) retention: Option<Arc<Retention<T>>>,
+
+    // the listener of the currently waiting publish_async call / sink flush,
+    // stored here to make polling cancellation-safe
+    space_listener: Option<EventListener>,
+}
+
+impl<T, P: PendingBuffer<T>> Outbox<T, P> {
+    pub(crate) fn new(
+        tail: &Arc<Tail<T>>,
+        shared: &Arc<Shared>,
+        pending: P,
+        retention: Option<Arc<Retention<T>>>,
+    ) -> Self {
+        Outbox {
+            tail: Arc::clone(tail),
+            shared: Arc::clone(shared),
+            pending,
+            retention,
+            space_listener: None,
+        }
+    }
+
+    /// Returns an outbox for another handle of the same queue,
+    /// without any pending revisions.
+    pub(crate) fn fork<Q: PendingBuffer<T>>(&self) -> Outbox<T, Q> {
+        Outbox::new(
+            &self.tail,
+            &self.shared,
+            Default::default(),
+            self.retention.clone(),
+        )
+    }
+
+    #[inline]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::SeqCst)
+    }
+
+    #[inline(always)]
+    pub(crate) fn enqueue(&mut self, pending: T) -> Result<(), QueueClosedError> {
+        if self.is_closed() {
+            return Err(QueueClosedError);
+        }
+        self.pending.push(pending);
+        Ok(())
+    }
+
+    pub(crate) fn enqueue_many<I>(&mut self, pending: I) -> Result<(), QueueClosedError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        if self.is_closed() {
+            return Err(QueueClosedError);
+        }
+        self.pending.extend(pending);
+        Ok(())
+    }
+
+    /// Publishes all pending revisions at the tail hint, after all revisions
+    /// which were published in the meantime, see [`publish_at_tail`].
+    #[inline]
+    pub(crate) fn flush(&mut self) -> Result<(), PublishError> {
+        publish_at_tail(&self.shared, &self.tail, &self.retention, &mut self.pending)
+    }
+}
+
+/// Publishes `pending` at the tail hint, and skips all revisions which were
+/// published in the meantime. If the queue is bounded, as many pending
+/// revisions as possible are published, and the rest is kept.
+pub(crate) fn publish_at_tail<T, P: PendingBuffer<T>>(
+    shared: &Shared,
+    tail: &Tail<T>,
+    retention: &Option<Arc<Retention<T>>>,
+    pending: &mut P,
+) -> Result<(), PublishError> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let (mut cur, mut pos) = tail.get();
+    let ret = loop {
+        // the revisions of handles which don't consume aren't tracked
+        match publish_intern(shared, &mut cur, &mut pos, pending, true, 0, None) {
+            Attempt::Done if pending.is_empty() => break Ok(()),
+            Attempt::Done | Attempt::Incoming(_) => {}
+            Attempt::Failed(e) => break Err(e),
+        }
+    };
+    tail.update(cur, pos);
+    if let Some(retention) = retention {
+        retention.advance(pos, shared);
+    }
+    ret
+}
+
+/// A handle which publishes the pending revisions of its [`Outbox`].
+pub(crate) trait Publish<T, P> {
+    fn outbox(&mut self) -> &mut Outbox<T, P>;
+
+    /// Publishes as many pending revisions as possible,
+    /// like `Queue::try_publish`.
+    fn publish_pending(&mut self) -> Result<(), PublishError>;
+
+    /// Returns `true` if this handle consumes revisions, its own
+    /// position is ignored while it publishes, see `Shared::free_capacity`.
+    #[inline]
+    fn subscribes(&self) -> bool {
+        false
+    }
+}
+
+/// Polls for publishing all pending revisions of `handle`,
+/// see `Queue::poll_publish`.
+pub(crate) fn poll_publish<T, P, H>(
+    handle: &mut H,
+    cx: &mut Context<'_>,
+) -> Poll<Result<(), QueueClosedError>>
+where
+    P: PendingBuffer<T>,
+    H: Publish<T, P>,
+{
+    let subscribes = handle.subscribes();
+    loop {
+        let ret = handle.publish_pending();
+        let outbox = handle.outbox();
+        match ret {
+            Ok(()) => {
+                outbox.space_listener = None;
+                return Poll::Ready(Ok(()));
+            }
+            Err(PublishError::Closed) => {
+                outbox.space_listener = None;
+                return Poll::Ready(Err(QueueClosedError));
+            }
+            Err(PublishError::Full) => {}
+        }
+        match &mut outbox.space_listener {
+            None => {
+                outbox.space_listener =
+                    Some(outbox.shared.listen_space(outbox.tail.get().1, subscribes))
+            }
+            Some(l) => match Pin::new(l).poll(cx) {
+                Poll::Ready(()) => outbox.space_listener = None,
+                Poll::Pending => return Poll::Pending,
+            },
+        }
+    }
+}
+
+/// Implements `Sink::poll_ready` for `handle`, which only waits
+/// if the queue is bounded.
+#[cfg(feature = "sink")]
+pub(crate) fn poll_ready<T, P, H>(
+    handle: &mut H,
+    cx: &mut Context<'_>,
+) -> Poll<Result<(), QueueClosedError>>
+where
+    P: PendingBuffer<T>,
+    H: Publish<T, P>,
+{
+    let outbox = handle.outbox();
+    if outbox.shared.capacity.is_some() {
+        poll_publish(handle, cx)
+    } else if outbox.is_closed() {
+        Poll::Ready(Err(QueueClosedError))
+    } else {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{NextRevision, RevisionRef};
+use alloc::{collections::VecDeque, sync::Arc};
+
+/// The positions of revisions which were published by a queue instance,
+/// but not skipped by it yet, see `Queue::publish_intern`.
+///
+/// Consecutive publishes which aren't interleaved with revisions of other
+/// handles are merged into one range, thus there are never more ranges
+/// than unconsumed revisions of other handles in between.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OwnRevisions {
+    // disjoint, ascending ranges of positions
+    ranges: VecDeque<(u64, u64)>,
+
+    // the total number of positions in $ranges
+    len: u64,
+}
+
+impl OwnRevisions {
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Appends the range `first..end`, which must follow all other ranges.
+    pub(crate) fn push(&mut self, first: u64, end: u64) {
+        self.len += end - first;
+        match self.ranges.back_mut() {
+            Some(last) if last.1 == first => last.1 = end,
+            _ => self.ranges.push_back((first, end)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn front(&self) -> Option<(u64, u64)> {
+        self.ranges.front().copied()
+    }
+
+    /// Returns `true` if the revision at `pos` is an own one, the ranges
+    /// before it are forgotten. `pos` must not decrease between calls.
+    fn contains_next(&mut self, pos: u64) -> bool {
+        while matches!(self.front(), Some((_, end)) if end <= pos) {
+            self.pop_front();
+        }
+        matches!(self.front(), Some((first, _)) if first <= pos)
+    }
+
+    pub(crate) fn pop_front(&mut self) {
+        if let Some((first, end)) = self.ranges.pop_front() {
+            self.len -= end - first;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        *self = Default::default();
+    }
+
+    /// Returns the number of positions at or after `pos`. Only the ranges
+    /// which start before `pos` are visited, which is usually none of them.
+    pub(crate) fn count_from(&self, pos: u64) -> u64 {
+        let mut ret = self.len;
+        for &(first, end) in &self.ranges {
+            if first >= pos {
+                break;
+            }
+            ret -= end.min(pos) - first;
+        }
+        ret
+    }
+}
+
+/// Walks the revision chain on behalf of a queue instance, starting at
+/// the cell `cur` with the position `pos`, and yields the revisions which
+/// weren't published by it, see `Queue::walk`.
+pub(crate) struct Walk<'a, T> {
+    cur: NextRevision<T>,
+    pos: u64,
+    own: &'a mut OwnRevisions,
+}
+
+impl<'a, T> Walk<'a, T> {
+    pub(crate) fn new(cur: NextRevision<T>, pos: u64, own: &'a mut OwnRevisions) -> Self {
+        Walk { cur, pos, own }
+    }
+
+    /// Returns the cell and position at which the walk stopped.
+    #[inline]
+    pub(crate) fn finish(self) -> (NextRevision<T>, u64) {
+        (self.cur, self.pos)
+    }
+
+    /// Moves past the next revision, if any was published, and
+    /// returns it and whether it is an own one.
+    fn step(&mut self) -> Option<(NextRevision<T>, bool)> {
+        let next = match self.cur.get() {
+            Some(Some(x)) => Arc::clone(&x.next),
+            _ => return None,
+        };
+        let is_own = self.own.contains_next(self.pos);
+        self.pos += 1;
+        Some((core::mem::replace(&mut self.cur, next), is_own))
+    }
+
+    /// Moves past the own revisions, as long as they are next. Stops at
+    /// the end of the chain, which is only reached if the chain was replaced.
+    pub(crate) fn skip_own(&mut self) {
+        while self.own.contains_next(self.pos) && self.step().is_some() {}
+    }
+}
+
+impl<T> Iterator for Walk<'_, T> {
+    type Item = RevisionRef<T>;
+
+    fn next(&mut self) -> Option<RevisionRef<T>> {
+        loop {
+            if let (inner, false) = self.step()? {
+                return Some(RevisionRef { inner });
+            }
+        }
+    }
+}
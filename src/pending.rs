@@ -0,0 +1,139 @@
+use alloc::vec::Vec;
+
+/// A container for revisions which were enqueued, but not published yet,
+/// see [`Queue::with_pending_buffer`](crate::Queue::with_pending_buffer).
+///
+/// The revisions are published in the order in which they were added.
+/// `Vec<T>` is the default container.
+pub trait PendingBuffer<T>: Default + Extend<T> {
+    /// Returns the pending revisions, in publishing order.
+    fn as_slice(&self) -> &[T];
+
+    /// Appends a revision.
+    fn push(&mut self, revision: T);
+
+    /// Removes and returns the last revision, if any.
+    fn pop(&mut self) -> Option<T>;
+
+    /// Splits off the revisions starting at index `at`, and returns them.
+    /// Only called with `at < self.len()`.
+    fn split_off(&mut self, at: usize) -> Self;
+
+    /// Removes all revisions.
+    fn clear(&mut self);
+
+    /// Removes all revisions and appends them to `other`, in publishing order.
+    fn drain_into<E: Extend<T>>(&mut self, other: &mut E) {
+        let mut revisions = Vec::with_capacity(self.len());
+        while let Some(x) = self.pop() {
+            revisions.push(x);
+        }
+        other.extend(revisions.into_iter().rev());
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+}
+
+impl<T> PendingBuffer<T> for Vec<T> {
+    #[inline]
+    fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    #[inline]
+    fn push(&mut self, revision: T) {
+        Vec::push(self, revision)
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        Vec::pop(self)
+    }
+
+    #[inline]
+    fn split_off(&mut self, at: usize) -> Self {
+        Vec::split_off(self, at)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        Vec::clear(self)
+    }
+
+    #[inline]
+    fn drain_into<E: Extend<T>>(&mut self, other: &mut E) {
+        other.extend(self.drain(..))
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        Vec::is_empty(self)
+    }
+}
+
+/// A buffer holding at most one revision, used by `SharedPublisher`,
+/// which publishes each revision on its own and thus needs no allocation.
+#[derive(Debug)]
+pub(crate) struct Single<T>(pub(crate) Option<T>);
+
+impl<T> Default for Single<T> {
+    #[inline]
+    fn default() -> Self {
+        Single(None)
+    }
+}
+
+impl<T> Extend<T> for Single<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for i in iter {
+            self.push(i);
+        }
+    }
+}
+
+impl<T> PendingBuffer<T> for Single<T> {
+    #[inline]
+    fn as_slice(&self) -> &[T] {
+        self.0.as_slice()
+    }
+
+    #[inline]
+    fn push(&mut self, revision: T) {
+        assert!(self.0.is_none(), "a single revision is already pending");
+        self.0 = Some(revision);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        self.0.take()
+    }
+
+    #[inline]
+    fn split_off(&mut self, _at: usize) -> Self {
+        // at == 0, thus everything is split off
+        core::mem::take(self)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    #[inline]
+    fn drain_into<E: Extend<T>>(&mut self, other: &mut E) {
+        other.extend(self.0.take())
+    }
+}
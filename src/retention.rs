@@ -0,0 +1,113 @@
+use crate::{NextRevision, RevisionNode, Shared};
+use alloc::sync::{Arc, Weak};
+use once_cell::sync::OnceCell;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub(crate) type WeakRevision<T> = Weak<OnceCell<Option<RevisionNode<T>>>>;
+
+/// The starting point of a new subscription, see [`Queue::subscribe_from`](crate::Queue::subscribe_from).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Replay {
+    /// Start after the newest published revision,
+    /// thus only revisions published afterwards are received.
+    Head,
+
+    /// Start with the newest `n` published revisions (or less,
+    /// if less revisions are retained).
+    LastN(usize),
+
+    /// Start with the oldest retained revision.
+    All,
+}
+
+/// The position of a consuming handle in the revision chain.
+#[derive(Debug)]
+pub(crate) enum Next<T> {
+    /// Retains all following revisions.
+    Strong(NextRevision<T>),
+
+    /// Doesn't retain anything. Used by queues with a retention limit while
+    /// they are idle, the revisions are retained by the [`Retention`] window.
+    Weak(WeakRevision<T>),
+}
+
+impl<T> Clone for Next<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Next::Strong(x) => Next::Strong(Arc::clone(x)),
+            Next::Weak(x) => Next::Weak(Weak::clone(x)),
+        }
+    }
+}
+
+impl<T> Next<T> {
+    /// Returns `None` if the revision was evicted from the retention window.
+    pub(crate) fn upgrade(&self) -> Option<NextRevision<T>> {
+        match self {
+            Next::Strong(x) => Some(Arc::clone(x)),
+            Next::Weak(x) => x.upgrade(),
+        }
+    }
+}
+
+/// The retention window of a queue with a retention limit,
+/// see [`Queue::with_retention`](crate::Queue::with_retention),
+/// or of a queue which retains its history,
+/// see [`Queue::with_history`](crate::Queue::with_history).
+#[derive(Debug)]
+pub(crate) struct Retention<T> {
+    // None if everything since the last marker is retained
+    limit: Option<u64>,
+
+    // the oldest retained revision and its position,
+    // which retains all following revisions
+    start: Mutex<(NextRevision<T>, u64)>,
+}
+
+impl<T> Retention<T> {
+    pub(crate) fn new(limit: Option<u64>, start: &NextRevision<T>) -> Self {
+        Retention {
+            limit,
+            start: Mutex::new((Arc::clone(start), 0)),
+        }
+    }
+
+    pub(crate) fn start(&self) -> MutexGuard<'_, (NextRevision<T>, u64)> {
+        // the window is always consistent
+        self.start.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Evicts old revisions after revisions up to position `end` were published.
+    pub(crate) fn advance(&self, end: u64, shared: &Shared) {
+        let limit = match self.limit {
+            Some(x) => x,
+            None => return,
+        };
+        let mut start = self.start();
+        let orig_pos = start.1;
+        while end.saturating_sub(start.1) > limit {
+            let next = match start.0.get() {
+                Some(Some(x)) => Arc::clone(&x.next),
+                _ => break,
+            };
+            start.0 = next;
+            start.1 += 1;
+        }
+        let pos = start.1;
+        drop(start);
+        if pos != orig_pos {
+            shared.notify_evicted(pos);
+        }
+    }
+
+    /// Evicts all revisions before the cell `cur` with the position `pos`,
+    /// unless they were already evicted.
+    pub(crate) fn mark(&self, cur: &NextRevision<T>, pos: u64, shared: &Shared) {
+        let mut start = self.start();
+        if pos > start.1 {
+            *start = (Arc::clone(cur), pos);
+            drop(start);
+            shared.notify_evicted(pos);
+        }
+    }
+}
@@ -0,0 +1,571 @@
+use crate::{
+    close_intern,
+    outbox::{self, Outbox, Publish},
+    pending::Single,
+    retention::{Next, Retention},
+    tail::Tail,
+    Cursor, Events, Lagged, PendingBuffer, PublishError, Queue, QueueClosedError, Replay,
+    RevisionRef, Shared, TimeoutError, TryPublishError,
+};
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(any(feature = "sink", feature = "stream"))]
+use core::pin::Pin;
+use core::{
+    sync::atomic::Ordering,
+    task::{Context, Poll},
+};
+use std::time::{Duration, Instant};
+
+/// Creates a new queue, split into a [`Publisher`] and a [`Subscriber`].
+/// This is equivalent to `Queue::new().split()`.
+#[inline]
+pub fn channel<T>() -> (Publisher<T>, Subscriber<T>) {
+    Queue::new().split()
+}
+
+/// The publishing half of a queue, see [`Queue::split`].
+///
+/// A publisher shares the hint where the end of the revision chain is with
+/// all other handles of the queue, thus publishing doesn't depend on the
+/// number of revisions which weren't consumed yet.
+///
+/// Unlike a [`Queue`], a publisher never retains any revisions, thus it
+/// should be preferred as sink of a pipeline.
+#[derive(Debug)]
+pub struct Publisher<T, P = Vec<T>> {
+    // the pending revisions, and where to publish them
+    outbox: Outbox<T, P>,
+}
+
+impl<T, P: PendingBuffer<T>> Clone for Publisher<T, P> {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.outbox.shared.publishers.fetch_add(1, Ordering::SeqCst);
+        Publisher {
+            outbox: self.outbox.fork(),
+        }
+    }
+}
+
+// the publisher never pins any revision data
+impl<T, P> Unpin for Publisher<T, P> {}
+
+impl<T, P> Drop for Publisher<T, P> {
+    #[inline]
+    fn drop(&mut self) {
+        self.outbox.shared.drop_publisher();
+    }
+}
+
+impl<T, P: PendingBuffer<T>> Publish<T, P> for Publisher<T, P> {
+    #[inline]
+    fn outbox(&mut self) -> &mut Outbox<T, P> {
+        &mut self.outbox
+    }
+
+    #[inline]
+    fn publish_pending(&mut self) -> Result<(), PublishError> {
+        self.flush()
+    }
+}
+
+impl<T, P: PendingBuffer<T>> Publisher<T, P> {
+    pub(crate) fn new(
+        tail: &Arc<Tail<T>>,
+        shared: &Arc<Shared>,
+        pending: P,
+        retention: Option<Arc<Retention<T>>>,
+    ) -> Self {
+        shared.publishers.fetch_add(1, Ordering::SeqCst);
+        Publisher {
+            outbox: Outbox::new(tail, shared, pending, retention),
+        }
+    }
+
+    /// This method enqueues the pending revision for publishing,
+    /// call [`flush`](Publisher::flush) to publish them.
+    /// Fails if the queue was [closed](Publisher::close).
+    #[inline]
+    pub fn enqueue(&mut self, pending: T) -> Result<(), QueueClosedError> {
+        self.outbox.enqueue(pending)
+    }
+
+    /// Enqueues multiple revisions for publishing at once,
+    /// see [`enqueue`](Publisher::enqueue).
+    #[inline]
+    pub fn enqueue_many<I>(&mut self, pending: I) -> Result<(), QueueClosedError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.outbox.enqueue_many(pending)
+    }
+
+    /// Returns the revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn pending(&self) -> &[T] {
+        self.outbox.pending.as_slice()
+    }
+
+    /// Returns the number of revisions which were enqueued,
+    /// but not published yet.
+    #[inline]
+    pub fn pending_len(&self) -> usize {
+        self.outbox.pending.len()
+    }
+
+    /// Takes back all revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn retract_pending(&mut self) -> P {
+        core::mem::take(&mut self.outbox.pending)
+    }
+
+    /// Discards all revisions which were enqueued, but not published yet.
+    #[inline]
+    pub fn clear_pending(&mut self) {
+        self.outbox.pending.clear();
+    }
+
+    /// Publishes all pending revisions.
+    /// Fails if the queue was [closed](Publisher::close), or if the queue is
+    /// [bounded](Queue::bounded) and not all pending revisions fit into it,
+    /// in which case the remaining pending revisions are kept.
+    #[inline]
+    pub fn flush(&mut self) -> Result<(), PublishError> {
+        self.outbox.flush()
+    }
+
+    /// Polls for publishing all pending revisions,
+    /// see [`flush_async`](Publisher::flush_async).
+    #[inline]
+    pub fn poll_publish(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), QueueClosedError>> {
+        outbox::poll_publish(self, cx)
+    }
+
+    /// Publishes all pending revisions like [`flush`](Publisher::flush),
+    /// but if the queue is [bounded](Queue::bounded), waits asynchronously
+    /// until the slowest subscriber caught up enough.
+    pub async fn flush_async(&mut self) -> Result<(), QueueClosedError> {
+        core::future::poll_fn(|cx| self.poll_publish(cx)).await
+    }
+
+    /// Closes the queue, see [`Queue::close`].
+    #[inline]
+    pub fn close(&self) -> bool {
+        close_intern(&self.outbox.shared, self.outbox.tail.get().0)
+    }
+
+    /// Returns `true` if the queue was closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.outbox.is_closed()
+    }
+
+    /// Returns the number of handles which are able to consume revisions
+    /// (queues and subscribers). If this returns 0, all subscribers are gone.
+    #[inline]
+    pub fn subscriber_count(&self) -> usize {
+        self.outbox.shared.subscribers.load(Ordering::SeqCst)
+    }
+}
+
+/// Publishing sink; [`poll_flush`](futures_sink::Sink::poll_flush) publishes
+/// all pending revisions, see [`Publisher::flush`].
+/// If the queue is [bounded](Queue::bounded), `poll_ready` waits until
+/// all previously sent revisions were published.
+#[cfg(feature = "sink")]
+impl<T, P: PendingBuffer<T>> futures_sink::Sink<T> for Publisher<T, P> {
+    type Error = QueueClosedError;
+
+    #[inline]
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        outbox::poll_ready(self.get_mut(), cx)
+    }
+
+    #[inline]
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        self.get_mut().enqueue(item)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_publish(cx)
+    }
+
+    #[inline]
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// A publisher which can be shared between threads,
+/// see [`Queue::shared_publisher`].
+///
+/// Unlike [`Publisher`], it doesn't buffer pending revisions, instead,
+/// each call to [`publish`](SharedPublisher::publish) directly appends
+/// a revision to the chain, thus it only needs `&self`.
+/// Like all other handles of the queue, it publishes at the shared hint
+/// where the end of the chain is, thus it doesn't need to walk the chain.
+#[derive(Debug)]
+pub struct SharedPublisher<T> {
+    tail: Arc<Tail<T>>,
+
+    shared: Arc<Shared>,
+
+    // the retention window, if the queue has a retention limit
+    retention: Option<Arc<Retention<T>>>,
+}
+
+impl<T> Clone for SharedPublisher<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.shared.publishers.fetch_add(1, Ordering::SeqCst);
+        SharedPublisher {
+            tail: Arc::clone(&self.tail),
+            shared: Arc::clone(&self.shared),
+            retention: self.retention.clone(),
+        }
+    }
+}
+
+impl<T> Drop for SharedPublisher<T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.shared.drop_publisher();
+    }
+}
+
+impl<T> SharedPublisher<T> {
+    pub(crate) fn new(
+        tail: &Arc<Tail<T>>,
+        shared: &Arc<Shared>,
+        retention: Option<Arc<Retention<T>>>,
+    ) -> Self {
+        shared.publishers.fetch_add(1, Ordering::SeqCst);
+        SharedPublisher {
+            tail: Arc::clone(tail),
+            shared: Arc::clone(shared),
+            retention,
+        }
+    }
+
+    /// Publishes `revision`.
+    /// Fails if the queue was [closed](SharedPublisher::close), or if the queue
+    /// is [bounded](Queue::bounded) and full, `revision` is returned in that case.
+    pub fn publish(&self, revision: T) -> Result<(), TryPublishError<T>> {
+        let mut pending = Single(Some(revision));
+        self.publish_intern(&mut pending)
+            .map_err(|e| Self::failed(e, pending))
+    }
+
+    /// Publishes `revision` like [`publish`](SharedPublisher::publish),
+    /// but if the queue is [bounded](Queue::bounded), waits asynchronously
+    /// until the slowest subscriber caught up enough.
+    /// Thus it only fails if the queue was closed.
+    pub async fn publish_async(&self, revision: T) -> Result<(), TryPublishError<T>> {
+        let mut pending = Single(Some(revision));
+        let mut listener = None;
+
+        loop {
+            match self.publish_intern(&mut pending) {
+                Ok(()) => return Ok(()),
+                Err(PublishError::Full) => {}
+                Err(e) => return Err(Self::failed(e, pending)),
+            }
+            match listener.take() {
+                None => listener = Some(self.shared.listen_space(self.tail.get().1, false)),
+                Some(l) => l.await,
+            }
+        }
+    }
+
+    /// Returns the revision of a failed publishing attempt,
+    /// which is kept as-is by it.
+    fn failed(e: PublishError, pending: Single<T>) -> TryPublishError<T> {
+        match pending.0 {
+            Some(x) => TryPublishError::new(e, x),
+            None => crate::perfect_unreachable(),
+        }
+    }
+
+    #[inline]
+    fn publish_intern(&self, pending: &mut Single<T>) -> Result<(), PublishError> {
+        outbox::publish_at_tail(&self.shared, &self.tail, &self.retention, pending)
+    }
+
+    /// Closes the queue, see [`Queue::close`].
+    #[inline]
+    pub fn close(&self) -> bool {
+        close_intern(&self.shared, self.tail.get().0)
+    }
+
+    /// Returns `true` if the queue was closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of handles which are able to consume revisions
+    /// (queues and subscribers). If this returns 0, all subscribers are gone.
+    #[inline]
+    pub fn subscriber_count(&self) -> usize {
+        self.shared.subscribers.load(Ordering::SeqCst)
+    }
+}
+
+/// The consuming half of a queue, see [`Queue::split`].
+#[derive(Debug)]
+#[must_use = "Subscriber does nothing unless you call .next() or some variation of it"]
+pub struct Subscriber<T> {
+    // a queue which is never used for publishing
+    inner: Queue<T>,
+}
+
+impl<T> Clone for Subscriber<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Subscriber {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Iterator for Subscriber<T> {
+    type Item = RevisionRef<T>;
+
+    #[inline]
+    fn next(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.next()
+    }
+}
+
+impl<T> Subscriber<T> {
+    pub(crate) fn new(
+        next: Next<T>,
+        pos: u64,
+        shared: &Arc<Shared>,
+        retention: Option<Arc<Retention<T>>>,
+        tail: &Arc<Tail<T>>,
+    ) -> Self {
+        Subscriber {
+            inner: Queue::with_position(next, pos, shared, false, retention, tail),
+        }
+    }
+
+    /// Creates the subscriber of a split queue, which takes over the
+    /// revisions the queue published itself, but didn't skip yet.
+    pub(crate) fn from_queue<P: PendingBuffer<T>>(queue: &mut Queue<T, P>) -> Self {
+        let retention = queue.outbox.retention.clone();
+        let mut ret = Subscriber::new(
+            queue.next.clone(),
+            Queue::position(queue),
+            &queue.outbox.shared,
+            retention,
+            &queue.outbox.tail,
+        );
+        ret.inner.own = core::mem::take(&mut queue.own);
+        ret
+    }
+
+    /// Polls for the next revision, see [`Queue::poll_next_revision`].
+    #[inline]
+    pub fn poll_next_revision(&mut self, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
+        self.inner.poll_next_revision(cx)
+    }
+
+    /// Waits asynchronously for an event to be published on the queue.
+    /// Only returns `None` if the queue was closed and all remaining
+    /// revisions were consumed, or if all publishers are gone.
+    #[inline]
+    pub async fn next_async(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.next_async().await
+    }
+
+    /// See [`Queue::peek`].
+    #[inline]
+    pub fn peek(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.peek()
+    }
+
+    /// See [`Queue::advance`].
+    #[inline]
+    pub fn advance(&mut self) -> bool {
+        self.inner.advance()
+    }
+
+    /// See [`Queue::poll_peek`].
+    #[inline]
+    pub fn poll_peek(&mut self, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
+        self.inner.poll_peek(cx)
+    }
+
+    /// See [`Queue::peek_async`].
+    #[inline]
+    pub async fn peek_async(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.peek_async().await
+    }
+
+    /// See [`Queue::skip_to_head`].
+    #[inline]
+    pub fn skip_to_head(&mut self) -> u64 {
+        self.inner.skip_to_head()
+    }
+
+    /// See [`Queue::latest`].
+    #[inline]
+    pub fn latest(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.latest()
+    }
+
+    /// See [`Queue::bookmark`].
+    #[inline]
+    pub fn bookmark(&mut self) -> Cursor<T> {
+        self.inner.bookmark()
+    }
+
+    /// See [`Queue::rewind_to`].
+    #[inline]
+    pub fn rewind_to(&mut self, cursor: &Cursor<T>) {
+        self.inner.rewind_to(cursor)
+    }
+
+    /// See [`Queue::next_batch`].
+    #[inline]
+    pub fn next_batch(&mut self, max: usize) -> Vec<RevisionRef<T>> {
+        self.inner.next_batch(max)
+    }
+
+    /// See [`Queue::poll_next_batch`].
+    #[inline]
+    pub fn poll_next_batch(
+        &mut self,
+        cx: &mut Context<'_>,
+        max: usize,
+    ) -> Poll<Vec<RevisionRef<T>>> {
+        self.inner.poll_next_batch(cx, max)
+    }
+
+    /// See [`Queue::next_batch_async`].
+    #[inline]
+    pub async fn next_batch_async(&mut self, max: usize) -> Vec<RevisionRef<T>> {
+        self.inner.next_batch_async(max).await
+    }
+
+    /// See [`Queue::events`].
+    #[inline]
+    pub fn events<E>(&mut self) -> Events<'_, T, E>
+    where
+        T: AsRef<[E]>,
+    {
+        self.inner.events()
+    }
+
+    /// See [`Queue::next_blocking`].
+    #[inline]
+    pub fn next_blocking(&mut self) -> Option<RevisionRef<T>> {
+        self.inner.next_blocking()
+    }
+
+    /// See [`Queue::next_timeout`].
+    #[inline]
+    pub fn next_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<RevisionRef<T>>, TimeoutError> {
+        self.inner.next_timeout(timeout)
+    }
+
+    /// See [`Queue::next_deadline`].
+    #[inline]
+    pub fn next_deadline(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Option<RevisionRef<T>>, TimeoutError> {
+        self.inner.next_deadline(deadline)
+    }
+
+    /// See [`Queue::try_next`].
+    #[inline]
+    pub fn try_next(&mut self) -> Result<Option<RevisionRef<T>>, Lagged> {
+        self.inner.try_next()
+    }
+
+    /// See [`Queue::poll_try_next`].
+    #[inline]
+    pub fn poll_try_next(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<RevisionRef<T>>, Lagged>> {
+        self.inner.poll_try_next(cx)
+    }
+
+    /// See [`Queue::try_next_async`].
+    #[inline]
+    pub async fn try_next_async(&mut self) -> Result<Option<RevisionRef<T>>, Lagged> {
+        self.inner.try_next_async().await
+    }
+
+    /// Creates a new subscriber, see [`Queue::subscribe_from`].
+    pub fn subscribe_from(&self, replay: Replay) -> Self {
+        let (next, pos) = self.inner.replay_start(replay);
+        let retention = self.inner.outbox.retention.clone();
+        Subscriber::new(
+            Next::Strong(next),
+            pos,
+            &self.inner.outbox.shared,
+            retention,
+            &self.inner.outbox.tail,
+        )
+    }
+
+    /// Creates a new subscriber, see [`Queue::resume_after`].
+    ///
+    /// # Panics
+    /// Panics if `revision` wasn't published on this queue.
+    pub fn resume_after(&self, revision: &RevisionRef<T>) -> Self {
+        let (next, pos) = RevisionRef::following(revision, &self.inner.outbox.shared);
+        let retention = self.inner.outbox.retention.clone();
+        Subscriber::new(
+            Next::Strong(next),
+            pos,
+            &self.inner.outbox.shared,
+            retention,
+            &self.inner.outbox.tail,
+        )
+    }
+
+    /// See [`Queue::position`].
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.inner.position()
+    }
+
+    /// See [`Queue::available`].
+    #[inline]
+    pub fn available(&self) -> u64 {
+        self.inner.available()
+    }
+
+    /// Returns `true` if the queue was closed.
+    /// Note that there might still be unconsumed revisions.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Returns the number of handles which are able to publish revisions
+    /// (queues and publishers). If this returns 0, all publishers are gone.
+    #[inline]
+    pub fn publisher_count(&self) -> usize {
+        self.inner.publisher_count()
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T> futures_core::Stream for Subscriber<T> {
+    type Item = RevisionRef<T>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<RevisionRef<T>>> {
+        self.get_mut().poll_next_revision(cx)
+    }
+}
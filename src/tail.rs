@@ -0,0 +1,58 @@
+use crate::NextRevision;
+use alloc::sync::Arc;
+use arc_swap::ArcSwap;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// A hint where the end of the revision chain is.
+///
+/// It is only updated after publishing via it, thus the end might be
+/// further, and the revisions in between are retained until then.
+/// Reading and updating it never blocks, because it is shared
+/// between all publishing handles.
+#[derive(Debug)]
+pub(crate) struct Tail<T> {
+    // the cell and its position
+    hint: ArcSwap<(NextRevision<T>, u64)>,
+
+    // the position of $hint, to skip outdated updates cheaply
+    pos: AtomicU64,
+}
+
+impl<T> Tail<T> {
+    /// Creates a hint at the end of the chain, starting the search at `cur`.
+    pub(crate) fn new(mut cur: NextRevision<T>, mut pos: u64) -> Self {
+        while let Some(Some(x)) = cur.get() {
+            let next = Arc::clone(&x.next);
+            cur = next;
+            pos += 1;
+        }
+        Tail {
+            hint: ArcSwap::from_pointee((cur, pos)),
+            pos: AtomicU64::new(pos),
+        }
+    }
+
+    pub(crate) fn get(&self) -> (NextRevision<T>, u64) {
+        let hint = self.hint.load();
+        (Arc::clone(&hint.0), hint.1)
+    }
+
+    /// Moves the hint forward to the cell `cur` with the position `pos`.
+    /// Does nothing if the hint is already at or after it.
+    pub(crate) fn update(&self, cur: NextRevision<T>, pos: u64) {
+        if pos <= self.pos.load(Ordering::Relaxed) {
+            return;
+        }
+        let new = Arc::new((cur, pos));
+        let mut hint = self.hint.load();
+        // never move the hint backwards, retry if it was updated concurrently
+        while pos > hint.1 {
+            let prev = self.hint.compare_and_swap(&hint, Arc::clone(&new));
+            if Arc::ptr_eq(&prev, &hint) {
+                self.pos.fetch_max(pos, Ordering::Relaxed);
+                break;
+            }
+            hint = prev;
+        }
+    }
+}
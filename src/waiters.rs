@@ -0,0 +1,81 @@
+use alloc::collections::BTreeMap;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use event_listener::{Event, EventListener};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Listeners which wait for a monotonic value (e.g. a position in the
+/// revision chain) to reach some key, grouped by that key.
+///
+/// Unlike a single [`Event`], notifying only wakes the listeners whose key
+/// was reached, thus e.g. a consumer which advances doesn't wake publishers
+/// which wait for a consumer further behind.
+#[derive(Debug)]
+pub(crate) struct Waiters {
+    lists: Mutex<BTreeMap<u64, Event>>,
+
+    // the number of entries in $lists, to skip locking if nobody waits
+    len: AtomicUsize,
+}
+
+impl Waiters {
+    pub(crate) fn new() -> Self {
+        Waiters {
+            lists: Mutex::new(BTreeMap::new()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    fn lists(&self) -> MutexGuard<'_, BTreeMap<u64, Event>> {
+        // the lists are always consistent
+        self.lists.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Starts listening until a value of at least `key` is reached.
+    /// The caller must re-check its condition afterwards.
+    pub(crate) fn listen(&self, key: u64) -> EventListener {
+        let mut lists = self.lists();
+        let ret = lists.entry(key).or_default().listen();
+        self.len.store(lists.len(), Ordering::SeqCst);
+        drop(lists);
+        // either the notifying side sees this listener, or the re-check
+        // of the caller sees its change, see is_waiting
+        fence(Ordering::SeqCst);
+        ret
+    }
+
+    /// Returns `true` if anyone might wait. Must be called after the
+    /// change which is notified afterwards, which must be a `SeqCst`
+    /// operation, or be followed by a `SeqCst` fence.
+    #[inline]
+    pub(crate) fn is_waiting(&self) -> bool {
+        self.len.load(Ordering::SeqCst) != 0
+    }
+
+    /// Wakes all listeners which wait for a value of at most `value`,
+    /// see [`is_waiting`](Waiters::is_waiting) for the ordering requirements.
+    pub(crate) fn notify(&self, value: u64) {
+        if !self.is_waiting() {
+            return;
+        }
+        let mut lists = self.lists();
+        if !matches!(lists.keys().next(), Some(&first) if first <= value) {
+            return;
+        }
+        let rest = match value.checked_add(1) {
+            Some(x) => lists.split_off(&x),
+            None => BTreeMap::new(),
+        };
+        let reached = core::mem::replace(&mut *lists, rest);
+        self.len.store(lists.len(), Ordering::SeqCst);
+        drop(lists);
+        for i in reached.values() {
+            i.notify(usize::MAX);
+        }
+    }
+
+    /// Wakes all listeners, e.g. after the queue was closed.
+    #[inline]
+    pub(crate) fn notify_all(&self) {
+        self.notify(u64::MAX);
+    }
+}
@@ -14,18 +14,18 @@ fn skip_and_publish<T: Send + 'static>(q: &mut Queue<T>) {
 #[test]
 fn simple() {
     let mut q = Queue::new();
-    q.enqueue(vec![0]);
+    q.enqueue(vec![0]).unwrap();
     skip_and_publish(&mut q);
 
     let mut l = q.clone();
     let mut marker = Vec::new();
-    marker.extend((&mut l).map(|i| (*i).clone()).flatten());
+    marker.extend((&mut l).flat_map(|i| (*i).clone()));
     assert!(marker.is_empty());
 
-    q.enqueue(vec![1]);
+    q.enqueue(vec![1]).unwrap();
     skip_and_publish(&mut q);
 
-    marker.extend((&mut l).map(|i| (*i).clone()).flatten());
+    marker.extend((&mut l).flat_map(|i| (*i).clone()));
     assert_eq!(marker, [1]);
 }
 
@@ -35,8 +35,8 @@ fn multi() {
     let l1 = q.clone();
     let mut l2 = q.clone();
 
-    q.enqueue(0);
-    q.enqueue(1);
+    q.enqueue(0).unwrap();
+    q.enqueue(1).unwrap();
     skip_and_publish(&mut q);
 
     let mut marker = Vec::new();
@@ -75,14 +75,14 @@ fn multithreaded() {
 
     let th1 = spt(&q);
     let th2 = spt(&q);
-    q.enqueue(1);
+    q.enqueue(1).unwrap();
     skip_and_publish(&mut q);
     thread::sleep(Duration::from_millis(60));
-    q.enqueue(2);
+    q.enqueue(2).unwrap();
     skip_and_publish(&mut q);
     thread::sleep(Duration::from_millis(30));
-    q.enqueue(3);
-    q.enqueue(4);
+    q.enqueue(3).unwrap();
+    q.enqueue(4).unwrap();
     skip_and_publish(&mut q);
     th1.join().unwrap();
     th2.join().unwrap();
@@ -94,13 +94,13 @@ fn mp() {
     let mut q2 = q1.clone();
 
     let (mut c1, mut c2) = (0, 0);
-    q1.enqueue(1);
+    q1.enqueue(1).unwrap();
     c1 += accumulate(&mut q1);
-    q2.enqueue(2);
+    q2.enqueue(2).unwrap();
     c2 += accumulate(&mut q2);
-    q1.enqueue(3);
+    q1.enqueue(3).unwrap();
     c1 += accumulate(&mut q1);
-    q2.enqueue(4);
+    q2.enqueue(4).unwrap();
     c2 += accumulate(&mut q2);
     c1 += accumulate(&mut q1);
     c2 += accumulate(&mut q2);
@@ -119,7 +119,7 @@ fn mtmp() {
         thread::spawn(move || {
             let mut c = 0;
             for i in publiv {
-                q.enqueue(i);
+                q.enqueue(i).unwrap();
                 c += accumulate(&mut q);
                 thread::sleep(Duration::from_millis(20));
             }
@@ -146,7 +146,7 @@ fn blocking() {
             let mut c = Vec::new();
             let plvl = publiv.len();
             for i in publiv {
-                q.enqueue(i);
+                q.enqueue(i).unwrap();
             }
             futures_lite::future::block_on(async {
                 while c.len() < plvl {
@@ -166,3 +166,1154 @@ fn blocking() {
     assert_eq!(th1.join().unwrap(), [2, 4]);
     assert_eq!(th2.join().unwrap(), [1, 3]);
 }
+
+#[test]
+#[cfg(feature = "stream")]
+fn stream() {
+    use futures_lite::stream::StreamExt;
+    let mut q1 = Queue::new();
+    let mut q2 = q1.clone();
+
+    q1.enqueue(1).unwrap();
+    q1.enqueue(2).unwrap();
+    skip_and_publish(&mut q1);
+    drop(q1);
+
+    // Queue is also an Iterator, thus the explicit trait path
+    let marker: Vec<u32> =
+        futures_lite::future::block_on(StreamExt::map(&mut q2, |i| *i).collect());
+    assert_eq!(marker, [1, 2]);
+}
+
+#[test]
+#[cfg(feature = "sink")]
+fn sink() {
+    use futures_sink::Sink;
+    use std::pin::Pin;
+
+    async fn send_all<S>(sink: &mut S, items: Vec<u32>)
+    where
+        S: Sink<u32, Error = revenq::QueueClosedError> + Unpin,
+    {
+        for i in items {
+            futures_lite::future::poll_fn(|cx| Pin::new(&mut *sink).poll_ready(cx))
+                .await
+                .unwrap();
+            Pin::new(&mut *sink).start_send(i).unwrap();
+        }
+        futures_lite::future::poll_fn(|cx| Pin::new(&mut *sink).poll_flush(cx))
+            .await
+            .unwrap();
+    }
+
+    let mut q1 = Queue::new();
+    let q2 = q1.clone();
+
+    futures_lite::future::block_on(send_all(&mut q1, vec![1, 2]));
+    assert_eq!(q1.pending_len(), 0);
+    assert!(q1.next().is_none());
+
+    let marker: Vec<u32> = q2.map(|i| *i).collect();
+    assert_eq!(marker, [1, 2]);
+
+    // revisions published by others are kept for the consuming side
+    let mut q1 = Queue::bounded(4);
+    let mut q2 = q1.clone();
+    q2.enqueue(1).unwrap();
+    q2.try_publish().unwrap();
+    futures_lite::future::block_on(send_all(&mut q1, vec![2]));
+    assert_eq!(accumulate(&mut q1), 1);
+    assert_eq!(accumulate(&mut q2), 2);
+
+    // the publisher of a split queue doesn't retain anything
+    let (mut publ, mut sub) = Queue::bounded(1).split();
+    let th = std::thread::spawn(move || {
+        let mut c = Vec::new();
+        while let Some(x) = sub.next_blocking() {
+            c.push(*x);
+        }
+        c
+    });
+    futures_lite::future::block_on(send_all(&mut publ, (0..10).collect()));
+    drop(publ);
+    assert_eq!(th.join().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn close() {
+    let mut q1 = Queue::new();
+    let mut q2 = q1.clone();
+
+    q1.enqueue(1).unwrap();
+    q1.enqueue(2).unwrap();
+    skip_and_publish(&mut q1);
+    assert!(q1.close());
+    assert!(!q2.close());
+    assert!(q2.is_closed());
+    assert_eq!(q1.enqueue(3), Err(revenq::QueueClosedError));
+
+    // q1 is still alive, but next_async doesn't block
+    let marker = futures_lite::future::block_on(async {
+        let mut marker = Vec::new();
+        while let Some(x) = q2.next_async().await {
+            marker.push(*x);
+        }
+        marker
+    });
+    assert_eq!(marker, [1, 2]);
+    assert!(q1.next().is_none());
+}
+
+#[test]
+fn split() {
+    let (mut p, mut s1) = revenq::channel();
+    let s2 = s1.clone();
+    assert_eq!(p.subscriber_count(), 2);
+    assert_eq!(s1.publisher_count(), 1);
+
+    p.enqueue(1).unwrap();
+    p.enqueue(2).unwrap();
+    p.flush().unwrap();
+    assert_eq!(accumulate(&mut s1), 3);
+
+    p.enqueue(3).unwrap();
+    p.flush().unwrap();
+    drop(p);
+    assert_eq!(s1.publisher_count(), 0);
+
+    // all publishers are gone, thus next_async doesn't block
+    let marker = futures_lite::future::block_on(async {
+        let mut marker = Vec::new();
+        let mut s2 = s2;
+        while let Some(x) = s2.next_async().await {
+            marker.push(*x);
+        }
+        marker
+    });
+    assert_eq!(marker, [1, 2, 3]);
+    drop(s1);
+
+    let mut q = Queue::new();
+    q.enqueue(4).unwrap();
+    let (mut p, s) = q.split();
+    p.flush().unwrap();
+    drop(s);
+    assert_eq!(p.subscriber_count(), 0);
+    p.enqueue(5).unwrap();
+    p.flush().unwrap();
+
+    // the handles are cloneable even if the revisions aren't
+    struct NoClone;
+    let (p, s) = revenq::channel::<NoClone>();
+    let _p2 = p.clone();
+    let _s2 = s.clone();
+    assert_eq!(p.subscriber_count(), 2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn drop_long_chain() {
+    let mut q1 = Queue::new();
+    let q2 = q1.clone();
+    let mut q3 = q1.clone();
+    // test threads have a 2 MiB stack, which a recursive drop of 100k
+    // revisions overflows (at least one stack frame per revision), thus
+    // this suffices to catch it, and runs much faster than 10M revisions
+    for _ in 0..100_000 {
+        q1.enqueue(()).unwrap();
+    }
+    skip_and_publish(&mut q1);
+    drop(q1);
+
+    // a lagging queue retains the whole chain
+    drop(q2);
+
+    // a lagging revision retains the rest of the chain
+    let first = q3.next().unwrap();
+    drop(q3);
+    drop(first);
+}
+
+#[test]
+fn bounded() {
+    let mut q = Queue::bounded(2);
+    let mut l = q.clone();
+
+    for i in 1..=3 {
+        q.enqueue(i).unwrap();
+    }
+    assert_eq!(q.try_publish(), Err(revenq::PublishError::Full));
+    assert_eq!(q.pending(), [3]);
+    assert_eq!(*l.next().unwrap(), 1);
+    assert_eq!(q.try_publish(), Ok(()));
+    assert_eq!(q.pending_len(), 0);
+
+    // the publishing queue itself never lags behind
+    let (mut p, s) = q.split();
+    p.enqueue(4).unwrap();
+    assert_eq!(p.flush(), Err(revenq::PublishError::Full));
+    drop(s);
+    let marker: Vec<u32> = (&mut l).map(|i| *i).collect();
+    assert_eq!(marker, [2, 3]);
+    assert_eq!(p.flush(), Ok(()));
+    assert_eq!(*l.next().unwrap(), 4);
+
+    // revisions which were published concurrently are kept
+    let mut a = Queue::bounded(10);
+    let mut b = a.clone();
+    b.enqueue(1).unwrap();
+    b.try_publish().unwrap();
+    a.enqueue(2).unwrap();
+    a.try_publish().unwrap();
+    let marker: Vec<u32> = (&mut a).map(|i| *i).collect();
+    assert_eq!(marker, [1]);
+    assert_eq!(accumulate(&mut b), 2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn bounded_own_position() {
+    use futures_lite::future::block_on;
+    use std::thread;
+
+    // the publishing queue doesn't wait for itself
+    let mut a = Queue::bounded(2);
+    let mut b = a.clone();
+    b.enqueue_many(1..=2).unwrap();
+    b.try_publish().unwrap();
+    a.enqueue(3).unwrap();
+    block_on(a.publish_async()).unwrap();
+    drop(b);
+    let marker: Vec<u32> = (&mut a).map(|i| *i).collect();
+    assert_eq!(marker, [1, 2]);
+
+    // ... even if it receives its own revisions
+    let mut a = Queue::bounded(2);
+    a.deliver_own(true);
+    a.enqueue_many(1..=3).unwrap();
+    block_on(a.publish_async()).unwrap();
+    assert_eq!(accumulate(&mut a), 6);
+
+    // but it still waits for the other subscribers
+    let mut a = Queue::bounded(1);
+    let mut l = a.clone();
+    a.enqueue(1).unwrap();
+    a.try_publish().unwrap();
+    a.enqueue(2).unwrap();
+    let th = thread::spawn(move || {
+        block_on(a.publish_async()).unwrap();
+        a
+    });
+    assert_eq!(*l.next_blocking().unwrap(), 1);
+    assert_eq!(*l.next_blocking().unwrap(), 2);
+    assert_eq!(th.join().unwrap().pending_len(), 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn bounded_async() {
+    use std::thread;
+    let mut q = Queue::bounded(1);
+    let l = q.clone();
+
+    let th = thread::spawn(move || {
+        let mut l = l;
+        let mut c = Vec::new();
+        futures_lite::future::block_on(async {
+            while let Some(x) = l.next_async().await {
+                c.push(*x);
+                thread::sleep(std::time::Duration::from_millis(5));
+            }
+        });
+        c
+    });
+
+    futures_lite::future::block_on(async {
+        for i in 0..10 {
+            q.enqueue(i).unwrap();
+            q.publish_async().await.unwrap();
+        }
+    });
+    drop(q);
+    assert_eq!(th.join().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn retention() {
+    use std::sync::Arc;
+    let marker = Arc::new(());
+    let mut q = Queue::with_retention(2);
+    let mut l = q.clone();
+
+    for _ in 0..5 {
+        q.enqueue(Arc::clone(&marker)).unwrap();
+    }
+    skip_and_publish(&mut q);
+    // the evicted revisions are freed, although $l is idle
+    assert_eq!(Arc::strong_count(&marker), 3);
+
+    assert_eq!(l.try_next().unwrap_err(), revenq::Lagged(3));
+    assert!(l.try_next().unwrap().is_some());
+    assert_eq!(l.by_ref().count(), 1);
+
+    // the queue itself never lags behind after publishing
+    q.enqueue(Arc::clone(&marker)).unwrap();
+    q.enqueue(Arc::clone(&marker)).unwrap();
+    skip_and_publish(&mut q);
+    let mut l2 = l.clone();
+    for _ in 0..3 {
+        q.enqueue(Arc::clone(&marker)).unwrap();
+    }
+    skip_and_publish(&mut q);
+    assert_eq!(l.try_next().unwrap_err(), revenq::Lagged(3));
+    drop(l);
+    drop(q);
+
+    // next_async skips silently
+    let c = futures_lite::future::block_on(async {
+        let mut c = 0;
+        while l2.next_async().await.is_some() {
+            c += 1;
+        }
+        c
+    });
+    assert_eq!(c, 2);
+}
+
+#[test]
+fn retention_silent() {
+    let mut q = Queue::with_retention(2);
+    let mut l = q.clone();
+    q.enqueue_many(0..5).unwrap();
+    skip_and_publish(&mut q);
+
+    // the iterator skips silently, thus nothing is reported afterwards
+    let items: Vec<_> = l.by_ref().map(|i| *i).collect();
+    assert_eq!(items, [3, 4]);
+    q.enqueue(5).unwrap();
+    skip_and_publish(&mut q);
+    assert_eq!(*l.try_next().unwrap().unwrap(), 5);
+
+    // the same applies to peeking
+    q.enqueue_many(6..10).unwrap();
+    skip_and_publish(&mut q);
+    assert_eq!(*l.peek().unwrap(), 8);
+    assert_eq!(*l.try_next().unwrap().unwrap(), 8);
+}
+
+#[test]
+fn seq() {
+    use revenq::RevisionRef;
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert_eq!(q.position(), 0);
+
+    q.enqueue(0).unwrap();
+    skip_and_publish(&mut q);
+    assert_eq!(q.position(), 1);
+    let mut l2 = q.clone();
+    for i in 1..4 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    assert_eq!(q.position(), 4);
+
+    let seqs: Vec<_> = (&mut l).map(|i| RevisionRef::seq(&i)).collect();
+    assert_eq!(seqs, [0, 1, 2, 3]);
+    assert_eq!(l.position(), 4);
+    let first = l2.next().unwrap();
+    assert_eq!((*first, RevisionRef::seq(&first)), (1, 1));
+    assert_eq!(l2.position(), 2);
+}
+
+#[test]
+fn introspection() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert_eq!((q.subscriber_count(), q.publisher_count()), (2, 2));
+
+    for i in 0..3 {
+        q.enqueue(i).unwrap();
+    }
+    assert_eq!(l.available(), 0);
+    skip_and_publish(&mut q);
+    assert_eq!(q.available(), 0);
+    assert_eq!(l.available(), 3);
+    l.next().unwrap();
+    assert_eq!(l.available(), 2);
+
+    let (publ, sub) = l.split();
+    assert_eq!((q.subscriber_count(), q.publisher_count()), (2, 2));
+    assert_eq!(sub.available(), 2);
+    drop(publ);
+    assert_eq!((q.subscriber_count(), q.publisher_count()), (2, 1));
+    drop(sub);
+    assert_eq!((q.subscriber_count(), q.publisher_count()), (1, 1));
+
+    let mut q = Queue::with_retention(2);
+    let l = q.clone();
+    for i in 0..5 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    assert_eq!(l.available(), 2);
+}
+
+#[test]
+fn replay() {
+    use revenq::Replay;
+    let mut q = Queue::with_history();
+    for i in 0..5 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+
+    let all: Vec<_> = q.subscribe_from(Replay::All).map(|i| *i).collect();
+    assert_eq!(all, [0, 1, 2, 3, 4]);
+    let last: Vec<_> = q.subscribe_from(Replay::LastN(2)).map(|i| *i).collect();
+    assert_eq!(last, [3, 4]);
+    let last: Vec<_> = q.subscribe_from(Replay::LastN(10)).map(|i| *i).collect();
+    assert_eq!(last, [0, 1, 2, 3, 4]);
+    let mut head = q.subscribe_from(Replay::Head);
+    assert_eq!(head.position(), 5);
+    assert!(head.next().is_none());
+
+    // free the history up to here
+    let mut l = q.subscribe_from(Replay::All);
+    l.next().unwrap();
+    l.next().unwrap();
+    l.mark_history();
+    let all: Vec<_> = q.subscribe_from(Replay::All).map(|i| *i).collect();
+    assert_eq!(all, [2, 3, 4]);
+
+    // subscribers share the history
+    let (mut publ, sub) = q.split();
+    publ.enqueue(5).unwrap();
+    publ.flush().unwrap();
+    let all: Vec<_> = sub.subscribe_from(Replay::All).map(|i| *i).collect();
+    assert_eq!(all, [2, 3, 4, 5]);
+
+    // the retention window limits the history
+    let mut q = Queue::with_retention(2);
+    for i in 0..5 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    let all: Vec<_> = q.subscribe_from(Replay::All).map(|i| *i).collect();
+    assert_eq!(all, [3, 4]);
+}
+
+#[test]
+fn peek() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert!(l.peek().is_none());
+    assert!(!l.advance());
+
+    q.enqueue(0).unwrap();
+    q.enqueue(1).unwrap();
+    skip_and_publish(&mut q);
+    assert_eq!(*l.peek().unwrap(), 0);
+    assert_eq!(*l.peek().unwrap(), 0);
+    assert_eq!(l.position(), 0);
+    assert!(l.advance());
+    assert_eq!(*l.peek().unwrap(), 1);
+    assert_eq!(*l.next().unwrap(), 1);
+    assert!(l.peek().is_none());
+
+    let (mut publ, mut sub) = l.split();
+    let handle = std::thread::spawn(move || {
+        futures_lite::future::block_on(async {
+            let x = *sub.peek_async().await.unwrap();
+            assert_eq!(x, *sub.next_async().await.unwrap());
+            sub.peek_async().await.is_none()
+        })
+    });
+    publ.enqueue(2).unwrap();
+    publ.flush().unwrap();
+    drop(publ);
+    drop(q);
+    assert!(handle.join().unwrap());
+}
+
+#[test]
+fn cursor() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    for i in 0..3 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+
+    assert_eq!(*l.next().unwrap(), 0);
+    let mut bookmark = l.bookmark();
+    assert_eq!(bookmark.position(), 1);
+    assert_eq!(accumulate(&mut l), 3);
+
+    // the cursor is independent of the queue
+    assert_eq!(*bookmark.peek().unwrap(), 1);
+    let items: Vec<_> = bookmark.clone().map(|i| *i).collect();
+    assert_eq!(items, [1, 2]);
+    assert_eq!(*bookmark.next().unwrap(), 1);
+    assert_eq!(bookmark.position(), 2);
+
+    // re-read the remaining revisions
+    l.rewind_to(&bookmark);
+    assert_eq!(l.position(), 2);
+    assert_eq!(accumulate(&mut l), 2);
+    assert!(l.next().is_none());
+}
+
+#[test]
+fn cursor_own() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    let bookmark = q.bookmark();
+    q.enqueue(10).unwrap();
+    q.try_publish().unwrap();
+    l.enqueue(11).unwrap();
+    l.try_publish().unwrap();
+    let items: Vec<_> = q.by_ref().map(|i| *i).collect();
+    assert_eq!(items, [11]);
+
+    // the own revisions are skipped again, even via the cursor of another instance
+    q.rewind_to(&bookmark);
+    let items: Vec<_> = q.by_ref().map(|i| *i).collect();
+    assert_eq!(items, [11]);
+    l.rewind_to(&bookmark);
+    let items: Vec<_> = l.by_ref().map(|i| *i).collect();
+    assert_eq!(items, [10]);
+
+    q.deliver_own(true);
+    q.rewind_to(&bookmark);
+    let items: Vec<_> = q.by_ref().map(|i| *i).collect();
+    assert_eq!(items, [10, 11]);
+}
+
+#[test]
+fn resume_after() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    for i in 0..4 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+
+    l.next().unwrap();
+    let last = l.next().unwrap();
+    drop(l);
+    let mut l = q.resume_after(&last);
+    assert_eq!(l.position(), 2);
+    assert_eq!(accumulate(&mut l), 5);
+
+    let (_publ, sub) = q.split();
+    let mut sub = sub.resume_after(&last);
+    assert_eq!(*sub.next().unwrap(), 2);
+
+    // revisions of another queue are rejected
+    let other = Queue::new();
+    let ret = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| other.resume_after(&last)));
+    assert!(ret.is_err());
+}
+
+#[test]
+fn skip_to_head() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert_eq!(l.skip_to_head(), 0);
+    assert!(l.latest().is_none());
+
+    for i in 0..4 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    let mut l2 = l.clone();
+    assert_eq!(l.skip_to_head(), 4);
+    assert_eq!(l.position(), 4);
+    assert!(l.next().is_none());
+    l2.next().unwrap();
+    let latest = l2.latest().unwrap();
+    assert_eq!((*latest, revenq::RevisionRef::seq(&latest)), (3, 3));
+    assert!(l2.latest().is_none());
+
+    // lagged revisions are counted, too
+    let mut q = Queue::with_retention(2);
+    let mut l = q.clone();
+    for i in 0..5 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    assert_eq!(l.skip_to_head(), 5);
+    assert!(l.try_next().unwrap().is_none());
+}
+
+#[test]
+fn next_batch() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert!(l.next_batch(4).is_empty());
+    for i in 0..5 {
+        q.enqueue(i).unwrap();
+    }
+    skip_and_publish(&mut q);
+    let batch: Vec<_> = l.next_batch(3).into_iter().map(|i| *i).collect();
+    assert_eq!(batch, [0, 1, 2]);
+
+    let (mut publ, mut sub) = l.split();
+    let handle = std::thread::spawn(move || {
+        futures_lite::future::block_on(async {
+            let mut batches = Vec::new();
+            loop {
+                let batch = sub.next_batch_async(10).await;
+                if batch.is_empty() {
+                    break batches;
+                }
+                batches.push(batch.into_iter().map(|i| *i).collect::<Vec<_>>());
+            }
+        })
+    });
+    drop(q);
+    publ.enqueue(5).unwrap();
+    publ.enqueue(6).unwrap();
+    publ.flush().unwrap();
+    drop(publ);
+    let batches: Vec<_> = handle.join().unwrap().into_iter().flatten().collect();
+    assert_eq!(batches, [3, 4, 5, 6]);
+
+    // the own revisions are skipped, and don't count towards the limit
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    for i in 0..6 {
+        let publ = if i % 2 == 0 { &mut q } else { &mut l };
+        publ.enqueue(i).unwrap();
+        publ.try_publish().unwrap();
+    }
+    let batch: Vec<_> = l.next_batch(2).into_iter().map(|i| *i).collect();
+    assert_eq!(batch, [0, 2]);
+    assert_eq!(l.position(), 3);
+    let batch: Vec<_> = l.next_batch(2).into_iter().map(|i| *i).collect();
+    assert_eq!(batch, [4]);
+    assert_eq!(l.position(), 6);
+}
+
+#[test]
+fn events() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    q.enqueue(vec![0, 1]).unwrap();
+    q.enqueue(vec![]).unwrap();
+    q.enqueue(vec![2]).unwrap();
+    skip_and_publish(&mut q);
+
+    let mut events = l.events();
+    let first = events.next().unwrap();
+    assert_eq!(*first, 0);
+    assert_eq!(
+        revenq::RevisionRef::seq(revenq::EventRef::revision(&first)),
+        0
+    );
+    let rest: Vec<_> = events.map(|i| *i).collect();
+    assert_eq!(rest, [1, 2]);
+    // the event keeps its revision alive
+    assert_eq!(*first, 0);
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+    assert_send_sync(&first);
+
+    q.enqueue(vec![3, 4]).unwrap();
+    skip_and_publish(&mut q);
+    drop(q);
+    let all = futures_lite::future::block_on(async {
+        let mut events = l.events();
+        let mut all = Vec::new();
+        while let Some(x) = events.next_async().await {
+            all.push(*x);
+        }
+        all
+    });
+    assert_eq!(all, [3, 4]);
+}
+
+#[test]
+fn pending() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    q.enqueue_many(0..3).unwrap();
+    assert_eq!(q.pending(), [0, 1, 2]);
+    assert_eq!(q.retract_pending(), [0, 1, 2]);
+    assert_eq!(q.pending_len(), 0);
+    q.enqueue_many(vec![3, 4]).unwrap();
+    q.clear_pending();
+    q.enqueue(5).unwrap();
+    skip_and_publish(&mut q);
+    assert_eq!(accumulate(&mut l), 5);
+
+    // unpublished revisions can be taken back after closing
+    let (mut publ, _sub) = l.split();
+    publ.enqueue_many(vec![6, 7]).unwrap();
+    assert_eq!(publ.pending_len(), 2);
+    publ.close();
+    assert_eq!(publ.enqueue_many(vec![8]), Err(revenq::QueueClosedError));
+    assert_eq!(publ.retract_pending(), [6, 7]);
+}
+
+// drops revisions which are equal to the previously enqueued one
+#[derive(Debug, Default)]
+struct Dedup(Vec<u32>);
+
+impl Extend<u32> for Dedup {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for i in iter {
+            revenq::PendingBuffer::push(self, i);
+        }
+    }
+}
+
+impl revenq::PendingBuffer<u32> for Dedup {
+    fn as_slice(&self) -> &[u32] {
+        &self.0
+    }
+
+    fn push(&mut self, revision: u32) {
+        if self.0.last() != Some(&revision) {
+            self.0.push(revision);
+        }
+    }
+
+    fn pop(&mut self) -> Option<u32> {
+        self.0.pop()
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        Dedup(self.0.split_off(at))
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[test]
+fn pending_buffer() {
+    let mut q = Queue::bounded(2);
+    let mut l = q.clone();
+    q.enqueue(0).unwrap();
+    let mut q = q.with_pending_buffer::<Dedup>();
+    q.enqueue_many(vec![0, 1, 1, 2]).unwrap();
+    assert_eq!(q.pending(), [0, 1, 2]);
+
+    // only the fitting revisions are published
+    assert_eq!(q.try_publish(), Err(revenq::PublishError::Full));
+    assert_eq!(q.pending(), [2]);
+    assert_eq!(accumulate(&mut l), 1);
+    q.try_publish().unwrap();
+
+    let (mut publ, _sub) = q.split();
+    publ.enqueue_many(vec![3, 3]).unwrap();
+    assert_eq!(publ.retract_pending().0, [3]);
+
+    // the lag which wasn't reported yet is kept
+    let mut q = Queue::with_retention(2);
+    let mut l = q.clone();
+    q.enqueue_many(0..5).unwrap();
+    skip_and_publish(&mut q);
+    l.enqueue(9).unwrap();
+    l.publish().unwrap();
+    let mut l = l.with_pending_buffer::<Dedup>();
+    assert_eq!(l.try_next().unwrap_err(), revenq::Lagged(4));
+    assert_eq!(accumulate(&mut l), 4);
+}
+
+#[test]
+fn publish_report() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    let report = q.publish().unwrap();
+    assert_eq!((report.first_seq, report.count), (0, 0));
+
+    l.enqueue_many(0..2).unwrap();
+    l.publish().unwrap();
+    q.enqueue_many(2..5).unwrap();
+    let report = q.publish().unwrap();
+    assert_eq!((report.first_seq, report.count), (2, 3));
+    assert!(report.skipped_incoming.is_empty());
+    assert_eq!(q.position(), 0);
+    assert_eq!(accumulate(&mut q), 1);
+    assert_eq!(q.position(), 5);
+    assert_eq!(accumulate(&mut l), 9);
+
+    let mut q = Queue::with_retention(8);
+    let mut l = q.clone();
+    l.enqueue_many(0..2).unwrap();
+    l.publish().unwrap();
+    q.enqueue(2).unwrap();
+    let report = q.publish().unwrap();
+    assert_eq!((report.first_seq, report.count), (2, 1));
+    // queues with a retention limit don't skip anything either
+    assert_eq!(q.position(), 0);
+    assert_eq!(accumulate(&mut q), 1);
+    assert_eq!(q.position(), 3);
+
+    let mut q = Queue::bounded(2);
+    let _l = q.clone();
+    q.enqueue_many(0..3).unwrap();
+    let report = q.publish().unwrap();
+    assert_eq!((report.first_seq, report.count), (0, 2));
+    assert_eq!(q.pending_len(), 1);
+    assert_eq!(q.publish().unwrap_err(), revenq::PublishError::Full);
+}
+
+#[test]
+fn publish_at_tail() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    let mut m = q.clone();
+    l.enqueue_many(0..1000).unwrap();
+    l.publish().unwrap();
+
+    // publishing doesn't consume the backlog
+    q.enqueue(1000).unwrap();
+    let report = q.publish().unwrap();
+    assert_eq!(report.first_seq, 1000);
+    assert_eq!(q.available(), 1000);
+    m.enqueue(1001).unwrap();
+    m.publish().unwrap();
+    q.enqueue(1002).unwrap();
+    q.publish().unwrap();
+    assert_eq!(q.available(), 1001);
+
+    // the own revisions are skipped
+    let got: Vec<_> = (&mut q).skip(998).map(|i| *i).collect();
+    assert_eq!(got, [998, 999, 1001]);
+    assert_eq!(q.position(), 1003);
+    assert_eq!(accumulate(&mut l), 1000 + 1001 + 1002);
+    assert_eq!(q.skip_to_head(), 0);
+
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    l.enqueue(0).unwrap();
+    l.publish().unwrap();
+    q.enqueue(1).unwrap();
+    q.publish().unwrap();
+    l.enqueue(2).unwrap();
+    l.publish().unwrap();
+    assert_eq!(q.latest().map(|i| *i), Some(2));
+    assert_eq!(q.position(), 3);
+
+    // the newest revision is an own one
+    l.enqueue(3).unwrap();
+    l.publish().unwrap();
+    q.enqueue(4).unwrap();
+    q.publish().unwrap();
+    assert_eq!(q.latest().map(|i| *i), Some(3));
+    assert_eq!(q.position(), 5);
+    assert!(q.latest().is_none());
+    q.deliver_own(true);
+    q.enqueue(5).unwrap();
+    q.publish().unwrap();
+    assert_eq!(q.latest().map(|i| *i), Some(5));
+
+    // evicted own revisions aren't counted
+    let mut q = Queue::with_retention(3);
+    let mut l = q.clone();
+    for i in 0..5 {
+        let publ = if i % 3 == 0 { &mut l } else { &mut q };
+        publ.enqueue(i).unwrap();
+        publ.publish().unwrap();
+    }
+    assert_eq!(q.available(), 1);
+    assert_eq!(q.try_next().unwrap_err(), revenq::Lagged(1));
+    assert_eq!(q.next().map(|i| *i), Some(3));
+    assert!(q.next().is_none());
+
+    // a publisher doesn't lose track of the end of the chain
+    let (mut publ, mut sub) = revenq::channel();
+    let mut other = publ.clone();
+    other.enqueue(0).unwrap();
+    other.flush().unwrap();
+    assert_eq!(sub.next().map(|i| *i), Some(0));
+    publ.enqueue(1).unwrap();
+    publ.flush().unwrap();
+    assert_eq!(sub.next().map(|i| *i), Some(1));
+}
+
+#[test]
+fn publish_at_head() {
+    use revenq::ConditionalPublishError;
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    q.enqueue(0).unwrap();
+    assert_eq!(q.try_publish_at_head().unwrap(), 0);
+
+    l.enqueue(1).unwrap();
+    match l.try_publish_at_head() {
+        Err(ConditionalPublishError::Conflict { incoming }) => assert_eq!(*incoming, 0),
+        x => panic!("unexpected result: {:?}", x),
+    }
+    assert_eq!(l.pending(), [1]);
+    assert_eq!(l.try_publish_at_head().unwrap(), 1);
+    assert_eq!(*q.next().unwrap(), 1);
+
+    l.enqueue(2).unwrap();
+    q.close();
+    assert!(matches!(
+        l.try_publish_at_head(),
+        Err(ConditionalPublishError::Closed)
+    ));
+
+    // all or nothing
+    let mut q = Queue::bounded(2);
+    let _l = q.clone();
+    q.enqueue_many(vec![1, 2, 3]).unwrap();
+    assert!(matches!(
+        q.try_publish_at_head(),
+        Err(ConditionalPublishError::Full)
+    ));
+    assert_eq!(q.pending(), [1, 2, 3]);
+    assert_eq!(q.retract_pending(), [1, 2, 3]);
+    q.enqueue_many(vec![1, 2]).unwrap();
+    assert_eq!(q.try_publish_at_head().unwrap(), 0);
+    assert_eq!(q.pending_len(), 0);
+}
+
+#[test]
+fn deliver_own() {
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    q.deliver_own(true);
+
+    l.enqueue(0).unwrap();
+    skip_and_publish(&mut l);
+    q.enqueue(1).unwrap();
+    q.enqueue(2).unwrap();
+    // publishing doesn't consume anything
+    q.try_publish().unwrap();
+    assert_eq!(q.position(), 0);
+    let report = {
+        q.enqueue(3).unwrap();
+        q.publish().unwrap()
+    };
+    assert_eq!((report.first_seq, report.count), (3, 1));
+    assert!(report.skipped_incoming.is_empty());
+
+    let all: Vec<_> = q.by_ref().map(|i| *i).collect();
+    assert_eq!(all, [0, 1, 2, 3]);
+    assert_eq!(accumulate(&mut l), 6);
+
+    // clones inherit the setting, iterating publishes and then delivers
+    let mut q2 = q.clone();
+    q2.enqueue(4).unwrap();
+    assert_eq!(*q2.next().unwrap(), 4);
+    assert!(q2.next().is_none());
+
+    q.enqueue(5).unwrap();
+    match q.try_publish_at_head() {
+        Err(revenq::ConditionalPublishError::Conflict { incoming }) => assert_eq!(*incoming, 4),
+        x => panic!("unexpected result: {:?}", x),
+    }
+    assert_eq!(q.try_publish_at_head().unwrap(), 5);
+    assert_eq!(*q.next().unwrap(), 5);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn publish_and_wait() {
+    use std::{thread, time::Duration};
+    let mut q = Queue::new();
+    let mut l1 = q.clone();
+    let l2 = q.clone();
+
+    let th = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        let x = futures_lite::future::block_on(l1.next_async()).unwrap();
+        assert_eq!(l1.position(), 1);
+        drop(l2);
+        *x
+    });
+    q.enqueue(1).unwrap();
+    futures_lite::future::block_on(q.publish_and_wait()).unwrap();
+    // l1 consumed the revision, l2 was dropped
+    assert_eq!(th.join().unwrap(), 1);
+
+    // nothing to wait for
+    q.enqueue(2).unwrap();
+    futures_lite::future::block_on(q.publish_and_wait()).unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn publish_and_wait_slow_drop() {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    struct SlowDrop;
+    impl Drop for SlowDrop {
+        fn drop(&mut self) {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    // the pending revision is dropped after l was unregistered
+    l.enqueue(SlowDrop).unwrap();
+    let th = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        drop(l);
+    });
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        q.enqueue(SlowDrop).unwrap();
+        let ret = futures_lite::future::block_on(q.publish_and_wait());
+        tx.send(ret).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(3)), Ok(Ok(())));
+    th.join().unwrap();
+}
+
+#[test]
+fn publish_and_wait_own() {
+    use futures_lite::future::{block_on, poll_once};
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    let mut p = q.clone();
+
+    q.enqueue(1).unwrap();
+    let mut fut = Box::pin(q.publish_and_wait());
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    assert_eq!(*p.next().unwrap(), 1);
+    p.enqueue(2).unwrap();
+    p.try_publish().unwrap();
+    assert_eq!(*l.next().unwrap(), 1);
+    // the revision of p doesn't have to be consumed
+    assert_eq!(block_on(poll_once(&mut fut)), Some(Ok(())));
+}
+
+#[test]
+fn publish_and_wait_snapshot() {
+    use futures_lite::future::{block_on, poll_once};
+    let mut q = Queue::bounded(1);
+    let mut l = q.clone();
+    q.enqueue(1).unwrap();
+    q.try_publish().unwrap();
+    // nothing to publish, l doesn't have to consume the previous revision
+    assert_eq!(block_on(poll_once(q.publish_and_wait())), Some(Ok(())));
+
+    q.enqueue(2).unwrap();
+    let mut fut = Box::pin(q.publish_and_wait());
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    // created while publishing, thus it isn't waited for
+    let mut m = l.clone();
+    assert_eq!(*l.next().unwrap(), 1);
+    assert_eq!(*m.next().unwrap(), 1);
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    assert_eq!(*l.next().unwrap(), 2);
+    assert_eq!(block_on(poll_once(&mut fut)), Some(Ok(())));
+    drop(fut);
+    assert_eq!(*m.next().unwrap(), 2);
+}
+
+#[test]
+fn publish_and_wait_retention() {
+    use futures_lite::future::{block_on, poll_once};
+    let mut q = Queue::with_retention(2);
+    let mut idle = q.clone();
+    let mut p = q.clone();
+
+    q.enqueue(1).unwrap();
+    let mut fut = Box::pin(q.publish_and_wait());
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    assert_eq!(*p.next().unwrap(), 1);
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    p.enqueue(2).unwrap();
+    p.try_publish().unwrap();
+    // the revision is still retained for the idle queue
+    assert_eq!(block_on(poll_once(&mut fut)), None);
+    p.enqueue(3).unwrap();
+    p.try_publish().unwrap();
+    // the idle queue won't receive it anymore
+    assert_eq!(block_on(poll_once(&mut fut)), Some(Ok(())));
+    drop(fut);
+    assert_eq!(idle.try_next().unwrap_err(), revenq::Lagged(1));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn next_timeout() {
+    use std::{thread, time::Duration};
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    assert_eq!(
+        l.next_timeout(Duration::from_millis(10)).unwrap_err(),
+        revenq::TimeoutError
+    );
+
+    let th = thread::spawn(move || {
+        let mut c = Vec::new();
+        while let Some(x) = l.next_blocking() {
+            c.push(*x);
+        }
+        c
+    });
+    q.enqueue(1).unwrap();
+    skip_and_publish(&mut q);
+    thread::sleep(Duration::from_millis(20));
+    q.enqueue(2).unwrap();
+    skip_and_publish(&mut q);
+    drop(q);
+    assert_eq!(th.join().unwrap(), [1, 2]);
+
+    // publishes while waiting
+    let (_publ, mut sub) = revenq::channel::<u32>();
+    let mut q = Queue::new();
+    let mut l = q.clone();
+    l.enqueue(3).unwrap();
+    let deadline = std::time::Instant::now() + Duration::from_millis(10);
+    assert!(l.next_deadline(deadline).is_err());
+    assert_eq!(*q.next_timeout(Duration::from_secs(1)).unwrap().unwrap(), 3);
+    assert!(sub.next_timeout(Duration::from_millis(1)).is_err());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn shared_publisher() {
+    use std::{sync::Arc, thread};
+    fn assert_sync<T: Sync>(_: &T) {}
+
+    let mut q = Queue::new();
+    let publ = Arc::new(q.shared_publisher());
+    assert_sync(&publ);
+    q.enqueue(0).unwrap();
+    skip_and_publish(&mut q);
+
+    let ths: Vec<_> = (0..4)
+        .map(|i| {
+            let publ = Arc::clone(&publ);
+            thread::spawn(move || {
+                for j in 0..100 {
+                    publ.publish(i * 100 + j).unwrap();
+                }
+            })
+        })
+        .collect();
+    for th in ths {
+        th.join().unwrap();
+    }
+    assert_eq!(q.available(), 400);
+    let mut seen: Vec<_> = q.by_ref().map(|i| *i).collect();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..400).collect::<Vec<_>>());
+
+    publ.close();
+    let e = publ.publish(400).unwrap_err();
+    assert_eq!(e.error(), revenq::PublishError::Closed);
+    assert_eq!(e.into_inner(), 400);
+    assert!(q.next_blocking().is_none());
+
+    // the revision is returned if it doesn't fit
+    let mut q = Queue::bounded(1);
+    let publ = q.shared_publisher();
+    publ.publish(0).unwrap();
+    assert_eq!(publ.publish(1), Err(revenq::TryPublishError::Full(1)));
+    assert_eq!(accumulate(&mut q), 0);
+    publ.publish(1).unwrap();
+}